@@ -0,0 +1,129 @@
+use proc_macro2::*;
+use quote::quote;
+use syn::meta::ParseNestedMeta;
+use syn::spanned::Spanned;
+use syn::*;
+
+/// How the output of an async function is returned to Lua.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum AsyncMode {
+	Yield,
+	Callback,
+	Promise,
+}
+
+/// Arguments of the `#[flatgrass::function]` attribute.
+#[derive(Default)]
+pub struct FuncArgs {
+	pub method: bool,
+	pub mode: Option<(AsyncMode, Span)>,
+}
+
+impl FuncArgs {
+	pub fn parse(&mut self, meta: ParseNestedMeta) -> Result<()> {
+		let mode = if meta.path.is_ident("method") {
+			self.method = true;
+			return Ok(());
+		} else if meta.path.is_ident("yield") {
+			AsyncMode::Yield
+		} else if meta.path.is_ident("callback") {
+			AsyncMode::Callback
+		} else if meta.path.is_ident("promise") {
+			AsyncMode::Promise
+		} else {
+			return Err(meta.error("unsupported function argument"));
+		};
+
+		match self.mode {
+			Some(_) => Err(meta.error("only one of `yield`, `callback` and `promise` can be used")),
+			None => {
+				self.mode = Some((mode, meta.path.span()));
+				Ok(())
+			}
+		}
+	}
+}
+
+/// Arguments of the `#[flatgrass::entry]` attribute.
+#[derive(Default)]
+pub struct EntryArgs {
+	pub tokio: Option<Expr>,
+}
+
+impl EntryArgs {
+	pub fn parse(&mut self, meta: ParseNestedMeta) -> Result<()> {
+		if meta.path.is_ident("tokio") {
+			self.tokio = Some(meta.value()?.parse()?);
+			Ok(())
+		} else {
+			Err(meta.error("unsupported entry argument"))
+		}
+	}
+}
+
+/// Attributes that can be put on function parameters and struct fields.
+#[derive(Default)]
+pub struct LuaAttrs {
+	pub name: Option<LitStr>,
+	pub default: Option<TokenStream>,
+	pub default_span: Option<Span>,
+}
+
+impl LuaAttrs {
+	/// Parses and removes the `#[lua(...)]` and `#[default]` attributes from the list.
+	pub fn extract(attrs: &mut Vec<Attribute>) -> Result<Self> {
+		let mut res = Self::default();
+		let mut error: Option<Error> = None;
+		attrs.retain(|attr| {
+			let parsed = if attr.path().is_ident("lua") {
+				res.parse_lua(attr)
+			} else if attr.path().is_ident("default") {
+				res.parse_default(attr)
+			} else {
+				return true;
+			};
+
+			if let Err(err) = parsed {
+				match &mut error {
+					Some(error) => error.combine(err),
+					None => error = Some(err),
+				}
+			}
+
+			false
+		});
+
+		match error {
+			Some(err) => Err(err),
+			None => Ok(res),
+		}
+	}
+
+	fn parse_lua(&mut self, attr: &Attribute) -> Result<()> {
+		attr.parse_nested_meta(|meta| {
+			if meta.path.is_ident("name") {
+				self.name = Some(meta.value()?.parse()?);
+				Ok(())
+			} else {
+				Err(meta.error("unsupported lua attribute"))
+			}
+		})
+	}
+
+	fn parse_default(&mut self, attr: &Attribute) -> Result<()> {
+		self.default_span = Some(attr.path().span());
+		self.default = Some(match &attr.meta {
+			Meta::Path(_) => quote! { ::core::default::Default::default() },
+			Meta::List(list) => {
+				let expr = list.parse_args::<Expr>()?;
+				quote! { #expr }
+			}
+			Meta::NameValue(meta) => {
+				let err = Error::new_spanned(meta, "expected `#[default]` or `#[default(...)]`");
+				return Err(err);
+			}
+		});
+
+		Ok(())
+	}
+}
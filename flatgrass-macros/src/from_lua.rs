@@ -0,0 +1,92 @@
+use crate::attrs::LuaAttrs;
+use proc_macro2::*;
+use quote::quote;
+use syn::*;
+
+pub fn generate_from_lua(input: &DeriveInput) -> TokenStream {
+	let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+	let ident = &input.ident;
+
+	let fields = match &input.data {
+		Data::Struct(DataStruct {
+			fields: Fields::Named(fields),
+			..
+		}) => fields,
+		_ => {
+			let err = Error::new(
+				ident.span(),
+				"FromLua can only be derived for structs with named fields",
+			);
+			return err.to_compile_error();
+		}
+	};
+
+	let mut errors = Vec::new();
+	let fields = fields.named.iter().map(|field| {
+		let mut attrs = field.attrs.clone();
+		let attrs = LuaAttrs::extract(&mut attrs).unwrap_or_else(|err| {
+			errors.push(err.to_compile_error());
+			LuaAttrs::default()
+		});
+
+		let field_ident = field.ident.as_ref().unwrap();
+		let name = match attrs.name {
+			Some(name) => name.value(),
+			None => field_ident.to_string(),
+		};
+
+		let missing = match attrs.default {
+			Some(default) => default,
+			None => quote! {
+				match ::flatgrass::lua::FromLua::no_value() {
+					::core::result::Result::Ok(__fg_value) => __fg_value,
+					::core::result::Result::Err(__fg_err) => {
+						return ::core::result::Result::Err(::flatgrass::lua::error::FromLuaError::bad_field(#name, __fg_err));
+					}
+				}
+			},
+		};
+
+		quote! {
+			#field_ident: match __fg_table.raw_get(#name) {
+				::flatgrass::lua::Value::Nil => #missing,
+				__fg_value => match ::flatgrass::lua::FromLua::from_lua(__fg_value) {
+					::core::result::Result::Ok(__fg_value) => __fg_value,
+					::core::result::Result::Err(__fg_err) => {
+						return ::core::result::Result::Err(::flatgrass::lua::error::FromLuaError::bad_field(#name, __fg_err));
+					}
+				},
+			}
+		}
+	});
+
+	let fields = fields.collect::<Vec<_>>();
+	quote! {
+		#(#errors)*
+
+		impl #impl_generics ::flatgrass::lua::FromLua for #ident #type_generics #where_clause {
+			type Err = ::flatgrass::lua::error::FromLuaError<'static>;
+
+			fn from_lua(__fg_value: ::flatgrass::lua::Value) -> ::core::result::Result<Self, Self::Err> {
+				let __fg_table = match __fg_value {
+					::flatgrass::lua::Value::Table(__fg_table) => __fg_table,
+					::flatgrass::lua::Value::Nil => ::flatgrass::lua::Table::new(),
+					__fg_value => {
+						return ::core::result::Result::Err(::flatgrass::lua::error::FromLuaError::expected_and_got_type(
+							::flatgrass::lua::Type::Table,
+							__fg_value.get_type(),
+						));
+					}
+				};
+
+				::core::result::Result::Ok(Self {
+					#(#fields),*
+				})
+			}
+
+			fn no_value() -> ::core::result::Result<Self, Self::Err> {
+				Self::from_lua(::flatgrass::lua::Value::Nil)
+			}
+		}
+	}
+}
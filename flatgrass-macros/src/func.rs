@@ -1,11 +1,12 @@
+use crate::attrs::{AsyncMode, EntryArgs, FuncArgs, LuaAttrs};
 use proc_macro2::*;
 use quote::{format_ident, quote};
 use syn::spanned::Spanned;
 use syn::*;
 
-pub fn generate_entry(func: &ItemFn) -> TokenStream {
+pub fn generate_entry(func: &ItemFn, entry_args: &EntryArgs) -> TokenStream {
 	let ident = format_ident!("{}", func.sig.ident.to_string());
-	let tokens = generate_func(func);
+	let tokens = generate_func(func, &FuncArgs::default());
 	let mut errors = Vec::new();
 
 	for param in &func.sig.generics.params {
@@ -24,13 +25,25 @@ pub fn generate_entry(func: &ItemFn) -> TokenStream {
 		}
 	}
 
+	let configure_tokio = entry_args.tokio.as_ref().map(|tokio| {
+		if cfg!(feature = "tokio") {
+			quote! { ::flatgrass::task::__fg_configure_tokio(#tokio); }
+		} else {
+			let err = Error::new(
+				tokio.span(),
+				"configuring Tokio requires the `tokio` feature",
+			);
+			err.to_compile_error()
+		}
+	});
+
 	let body = match errors.is_empty() {
 		false => quote! { 0 },
 		true => quote! {
+			#configure_tokio
 			if ::flatgrass::lua::Lua::enter(__fg_state, |__fg_lua| {
 				let __fg_func = ::flatgrass::lua::Function::new(::flatgrass::cfunction!(#ident));
-				__fg_lua.__fg_entry();
-				match ::flatgrass::lua::call!(__fg_func) {
+				match __fg_lua.__fg_entry().and_then(|()| ::flatgrass::lua::call!(__fg_func)) {
 					Ok(_) => false,
 					Err(__fg_err) => {
 						__fg_lua.stack().clear();
@@ -62,7 +75,7 @@ pub fn generate_entry(func: &ItemFn) -> TokenStream {
 
 pub fn generate_exit(func: &ItemFn) -> TokenStream {
 	let ident = format_ident!("{}", func.sig.ident.to_string());
-	let tokens = generate_func(func);
+	let tokens = generate_func(func, &FuncArgs::default());
 	let mut errors = Vec::new();
 
 	for param in &func.sig.generics.params {
@@ -118,12 +131,68 @@ pub fn generate_exit(func: &ItemFn) -> TokenStream {
 	}
 }
 
-pub fn generate_func(func: &ItemFn) -> TokenStream {
+pub fn generate_func(func: &ItemFn, func_args: &FuncArgs) -> TokenStream {
 	let (impl_generics, type_generics, where_clause) = func.sig.generics.split_for_impl();
 	let generics_turbofish = type_generics.as_turbofish();
 	let ident = format_ident!("{}", func.sig.ident.to_string());
 	let vis = &func.vis;
 	let mut errors = Vec::new();
+	let mut params = Vec::new();
+	let mut item = func.clone();
+
+	for (i, input) in item.sig.inputs.iter_mut().enumerate() {
+		let attrs = match input {
+			FnArg::Typed(pat) => LuaAttrs::extract(&mut pat.attrs),
+			FnArg::Receiver(recv) => LuaAttrs::extract(&mut recv.attrs),
+		};
+
+		match attrs {
+			Ok(attrs) => {
+				if let Some(name) = &attrs.name
+					&& func_args.method
+					&& i == 0
+				{
+					let err = Error::new(
+						name.span(),
+						"the receiver of a Lua method cannot be renamed",
+					);
+					errors.push(err.to_compile_error());
+				}
+
+				if let Some(span) = attrs.default_span {
+					if func_args.method && i == 0 {
+						let err = Error::new(
+							span,
+							"the receiver of a Lua method cannot have a default value",
+						);
+						errors.push(err.to_compile_error());
+					} else if let FnArg::Typed(pat) = input
+						&& !reads_argument(&pat.ty)
+					{
+						let err = Error::new(
+							span,
+							"only parameters read from the arguments can have a default value",
+						);
+						errors.push(err.to_compile_error());
+					}
+				}
+
+				params.push(attrs);
+			}
+			Err(err) => {
+				errors.push(err.to_compile_error());
+				params.push(LuaAttrs::default());
+			}
+		}
+	}
+
+	if func_args.method && func.sig.inputs.is_empty() {
+		let err = Error::new(
+			func.sig.span(),
+			"Lua methods need a parameter for their receiver",
+		);
+		errors.push(err.to_compile_error());
+	}
 
 	if let Some(unsafety) = &func.sig.unsafety {
 		let err = Error::new(unsafety.span(), "Lua functions cannot be unsafe");
@@ -138,15 +207,55 @@ pub fn generate_func(func: &ItemFn) -> TokenStream {
 			);
 			errors.push(err.to_compile_error());
 		}
+	} else if let Some((_, span)) = func_args.mode {
+		let err = Error::new(span, "only async Lua functions can have a return mode");
+		errors.push(err.to_compile_error());
 	}
 
 	let body = match errors.is_empty() {
 		false => quote! { 0 },
 		true => {
-			let args = func.sig.inputs.iter().map(|_| {
+			let args = params.iter().enumerate().map(|(i, attrs)| {
+				// parameters with a default value are read as an `Option`, falling back to the default when missing
+				let ty = match &attrs.default {
+					Some(_) => quote! { ::core::option::Option<_> },
+					None => quote! { _ },
+				};
+
+				let (param, value) = match &attrs.name {
+					_ if func_args.method && i == 0 => (
+						quote! {
+							<::flatgrass::lua::util::This<_> as ::flatgrass::lua::FnParam>::fn_param(__fg_lua, &mut __fg_arg, &mut __fg_upv)
+						},
+						quote! { ::flatgrass::lua::util::This(__fg_value) },
+					),
+					Some(name) => (
+						quote! {
+							<#ty as ::flatgrass::lua::FnParam>::fn_param_named(__fg_lua, &mut __fg_arg, &mut __fg_upv, #name)
+						},
+						quote! { __fg_value },
+					),
+					None => (
+						quote! {
+							<#ty as ::flatgrass::lua::FnParam>::fn_param(__fg_lua, &mut __fg_arg, &mut __fg_upv)
+						},
+						quote! { __fg_value },
+					),
+				};
+
+				let ok = match &attrs.default {
+					None => quote! {
+						::core::result::Result::Ok(#value) => __fg_value,
+					},
+					Some(default) => quote! {
+						::core::result::Result::Ok(::core::option::Option::Some(__fg_value)) => __fg_value,
+						::core::result::Result::Ok(::core::option::Option::None) => #default,
+					},
+				};
+
 				quote! {
-					match ::flatgrass::lua::FnParam::fn_param(__fg_lua, &mut __fg_arg, &mut __fg_upv) {
-						::core::result::Result::Ok(__fg_value) => __fg_value,
+					match #param {
+						#ok
 						::core::result::Result::Err(__fg_err) => {
 							__fg_lua.stack().clear();
 							__fg_lua.stack().push_any(__fg_err);
@@ -157,10 +266,48 @@ pub fn generate_func(func: &ItemFn) -> TokenStream {
 			});
 
 			let call = match &func.sig.asyncness {
-				Some(_) => quote! {
-					__fg_lua.async_runtime().spawn(#ident #generics_turbofish (#(#args),*)).detach();
-					::core::option::Option::Some(::flatgrass::lua::util::Return::Values(0))
-				},
+				Some(_) => {
+					let future = quote! { #ident #generics_turbofish (#(#args),*) };
+					let spawn = match func_args.mode.map(|(mode, _)| mode) {
+						None => quote! {
+							__fg_lua.async_runtime().__fg_spawn_detached(#future).map(|()| ::flatgrass::lua::util::Return::Values(0))
+						},
+						Some(AsyncMode::Yield) => quote! {
+							__fg_lua.async_runtime().__fg_spawn_yield(#future)
+								.map(|__fg_values| ::flatgrass::lua::util::Return::Yield(__fg_lua.stack().push_many(__fg_values)))
+						},
+						Some(AsyncMode::Callback) => quote! {
+							{
+								let __fg_future = #future;
+								let __fg_callback = match ::flatgrass::lua::FnParam::fn_param_named(__fg_lua, &mut __fg_arg, &mut __fg_upv, "callback") {
+									::core::result::Result::Ok(__fg_value) => __fg_value,
+									::core::result::Result::Err(__fg_err) => {
+										__fg_lua.stack().clear();
+										__fg_lua.stack().push_any(__fg_err);
+										return ::core::option::Option::None;
+									}
+								};
+
+								__fg_lua.async_runtime().__fg_spawn_callback(__fg_future, __fg_callback).map(|()| ::flatgrass::lua::util::Return::Values(0))
+							}
+						},
+						Some(AsyncMode::Promise) => quote! {
+							__fg_lua.async_runtime().__fg_spawn_promise(#future)
+								.map(|__fg_promise| ::flatgrass::lua::util::Return::Values(__fg_lua.stack().push_many([__fg_promise])))
+						},
+					};
+
+					quote! {
+						match #spawn {
+							::core::result::Result::Ok(__fg_ret) => ::core::option::Option::Some(__fg_ret),
+							::core::result::Result::Err(__fg_err) => {
+								__fg_lua.stack().clear();
+								__fg_lua.stack().push_any(__fg_err);
+								::core::option::Option::None
+							}
+						}
+					}
+				}
 				None => quote! {
 					match ::flatgrass::lua::FnReturn::fn_return(#ident #generics_turbofish (#(#args),*), __fg_lua) {
 						::core::result::Result::Ok(::flatgrass::lua::util::Return::Values(values)) =>
@@ -194,7 +341,7 @@ pub fn generate_func(func: &ItemFn) -> TokenStream {
 	};
 
 	quote! {
-		#func
+		#item
 
 		#(#errors)*
 
@@ -217,3 +364,17 @@ pub fn generate_func(func: &ItemFn) -> TokenStream {
 		}
 	}
 }
+
+/// Whether a parameter is read from the arguments rather than the upvalues or the whole stack.
+fn reads_argument(ty: &Type) -> bool {
+	match ty {
+		Type::Group(group) => reads_argument(&group.elem),
+		Type::Paren(paren) => reads_argument(&paren.elem),
+		Type::Path(path) => path
+			.path
+			.segments
+			.last()
+			.is_none_or(|segment| segment.ident != "Upvalue" && segment.ident != "Tuple"),
+		_ => true,
+	}
+}
@@ -1,6 +1,10 @@
 use proc_macro::TokenStream;
 
+mod attrs;
+mod from_lua;
 mod func;
+mod module;
+mod net;
 
 /// Marks a function as the entry point of your module.
 ///
@@ -8,6 +12,9 @@ mod func;
 /// For this to work, it needs to be declared at the root of your library and
 /// to be paired with another function marked with `#[flatgrass::exit]`.
 ///
+/// With the `tokio` feature, `#[flatgrass::entry(tokio = ...)]` configures the Tokio runtime
+/// with a `TokioBuilder`, before it is started.
+///
 /// # Examples
 ///
 /// ```
@@ -16,11 +23,20 @@ mod func;
 ///   printfg!("Hello from binary module!");
 /// }
 /// ```
+///
+/// ```
+/// #[flatgrass::entry(tokio = flatgrass::task::TokioBuilder::multi_thread().worker_threads(4))]
+/// pub fn entry() {
+///   printfg!("Hello from a multi-threaded module!");
+/// }
+/// ```
 #[proc_macro_attribute]
 pub fn entry(args: TokenStream, input: TokenStream) -> TokenStream {
-	let _ = syn::parse_macro_input!(args as syn::parse::Nothing);
+	let mut entry_args = attrs::EntryArgs::default();
+	let parser = syn::meta::parser(|meta| entry_args.parse(meta));
+	syn::parse_macro_input!(args with parser);
 	let func = syn::parse_macro_input!(input as syn::ItemFn);
-	func::generate_entry(&func).into()
+	func::generate_entry(&func, &entry_args).into()
 }
 
 /// Marks a function as the exit point of your module.
@@ -45,6 +61,19 @@ pub fn exit(args: TokenStream, input: TokenStream) -> TokenStream {
 
 /// Generates the necessary glue code to call a function from Lua.
 ///
+/// Parameters can be annotated with `#[default]` or `#[default(value)]` to be used when
+/// the argument is `nil` or missing, and with `#[lua(name = "...")]` to be named in error messages.
+///
+/// With `#[flatgrass::function(method)]`, the first parameter is the receiver of the method,
+/// and errors converting it are reported as a bad `self` rather than a bad argument.
+///
+/// Async functions are spawned on the async runtime, and errors they return are reported in the console.
+/// Their output can be returned to Lua with one of the following arguments:
+/// - `yield` yields the calling coroutine, and resumes it with the output or `nil` followed by the error.
+/// - `callback` takes a function as the last argument, called with `true` followed by the output
+///   or `false` followed by the error.
+/// - `promise` returns a [`Promise`](../flatgrass/task/struct.Promise.html) settled with the output.
+///
 /// # Examples
 ///
 /// ```
@@ -53,9 +82,135 @@ pub fn exit(args: TokenStream, input: TokenStream) -> TokenStream {
 ///   a + b
 /// }
 /// ```
+///
+/// ```
+/// #[flatgrass::function(method)]
+/// pub fn get_name(this: Table) -> Value {
+///   this.raw_get("name")
+/// }
+/// ```
+///
+/// ```
+/// #[flatgrass::function]
+/// pub fn circle_area(#[lua(name = "radius")] #[default(1.0)] radius: f64) -> f64 {
+///   std::f64::consts::PI * radius * radius
+/// }
+/// ```
+///
+/// ```
+/// #[flatgrass::function(yield)]
+/// pub async fn wait(secs: f64) -> f64 {
+///   flatgrass::task::time::sleep(Duration::from_secs_f64(secs)).await;
+///   secs
+/// }
+/// ```
 #[proc_macro_attribute]
 pub fn function(args: TokenStream, input: TokenStream) -> TokenStream {
-	let _ = syn::parse_macro_input!(args as syn::parse::Nothing);
+	let mut func_args = attrs::FuncArgs::default();
+	let parser = syn::meta::parser(|meta| func_args.parse(meta));
+	syn::parse_macro_input!(args with parser);
 	let func = syn::parse_macro_input!(input as syn::ItemFn);
-	func::generate_func(&func).into()
+	func::generate_func(&func, &func_args).into()
+}
+
+/// Exports the items of an inline module to Lua.
+///
+/// Public functions annotated with `#[flatgrass::function]`, public constants and statics,
+/// and public submodules annotated with `#[flatgrass::module]` are collected into a table.\
+/// Items are exported under their own name, unless annotated with `#[lua(name = "...")]`.
+///
+/// This generates two functions in the module: `table`, which creates the table,
+/// and `register`, which also sets it as a global and as the value returned by `require`.\
+/// These names are reserved, so the module cannot define other items named `table` or `register`.
+///
+/// # Examples
+///
+/// ```
+/// #[flatgrass::module(name = "mylib")]
+/// pub mod mylib {
+///   pub const VERSION: &str = "1.0.0";
+///
+///   #[flatgrass::function]
+///   pub fn add(a: f64, b: f64) -> f64 {
+///     a + b
+///   }
+///
+///   #[flatgrass::module]
+///   pub mod strings {
+///     #[flatgrass::function]
+///     #[lua(name = "upper")]
+///     pub fn to_upper(s: String) -> String {
+///       s.to_uppercase()
+///     }
+///   }
+/// }
+///
+/// #[flatgrass::entry]
+/// pub fn entry() {
+///   mylib::register();
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn module(args: TokenStream, input: TokenStream) -> TokenStream {
+	let mut module_args = module::ModuleArgs::default();
+	let parser = syn::meta::parser(|meta| module_args.parse(meta));
+	syn::parse_macro_input!(args with parser);
+	let module = syn::parse_macro_input!(input as syn::ItemMod);
+	module::generate_module(&module, &module_args).into()
+}
+
+/// Implements `FromLua` for a struct with named fields by reading each field from a table.
+///
+/// A missing table is treated as an empty one, which makes it convenient to
+/// accept a trailing table of named options in a function.\
+/// Fields can be annotated with `#[default]` or `#[default(value)]` to be used when
+/// the key is missing, and with `#[lua(name = "...")]` to read a different key.
+///
+/// # Examples
+///
+/// ```
+/// #[derive(FromLua)]
+/// pub struct Options {
+///   #[default(10.0)]
+///   segments: f64,
+///   #[lua(name = "color")]
+///   colour: Option<String>,
+/// }
+///
+/// #[flatgrass::function]
+/// pub fn draw(radius: f64, options: Options) {
+///   // ...
+/// }
+/// ```
+#[proc_macro_derive(FromLua, attributes(lua, default))]
+pub fn from_lua(input: TokenStream) -> TokenStream {
+	let input = syn::parse_macro_input!(input as syn::DeriveInput);
+	from_lua::generate_from_lua(&input).into()
+}
+
+/// Derives the `NetMessage` trait, encoding fields in order.
+///
+/// Integer fields use their full width unless given a number of bits with `#[net(bits = N)]`.\
+/// The message is named after the struct, unless renamed with `#[net(name = "...")]`.
+///
+/// # Examples
+///
+/// ```
+/// #[derive(NetMessage)]
+/// #[net(name = "my_addon.score")]
+/// pub struct Score {
+///   player: Entity,
+///   #[net(bits = 10)]
+///   points: u32,
+///   reason: Option<String>,
+/// }
+///
+/// net::receive(|score: Score, sender| {
+///   // ...
+/// })?;
+/// ```
+#[proc_macro_derive(NetMessage, attributes(net))]
+pub fn net_message(input: TokenStream) -> TokenStream {
+	let input = syn::parse_macro_input!(input as syn::DeriveInput);
+	net::generate_net_message(&input).into()
 }
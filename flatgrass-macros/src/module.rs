@@ -0,0 +1,172 @@
+use crate::attrs::LuaAttrs;
+use proc_macro2::*;
+use quote::quote;
+use syn::meta::ParseNestedMeta;
+use syn::spanned::Spanned;
+use syn::*;
+
+/// Arguments of the `#[flatgrass::module]` attribute.
+#[derive(Default)]
+pub struct ModuleArgs {
+	pub name: Option<LitStr>,
+}
+
+impl ModuleArgs {
+	pub fn parse(&mut self, meta: ParseNestedMeta) -> Result<()> {
+		if meta.path.is_ident("name") {
+			self.name = Some(meta.value()?.parse()?);
+			Ok(())
+		} else {
+			Err(meta.error("unsupported module argument"))
+		}
+	}
+}
+
+/// Checks if the attribute is `#[flatgrass::<name>]` or `#[<name>]`.
+fn is_flatgrass_attr(attr: &Attribute, name: &str) -> bool {
+	let segments = &attr.path().segments;
+	match segments.len() {
+		1 => segments[0].ident == name,
+		2 => segments[0].ident == "flatgrass" && segments[1].ident == name,
+		_ => false,
+	}
+}
+
+/// The identifier of an item that functions or types can collide with.
+fn item_ident(item: &Item) -> Option<&Ident> {
+	match item {
+		Item::Fn(item) => Some(&item.sig.ident),
+		Item::Const(item) => Some(&item.ident),
+		Item::Static(item) => Some(&item.ident),
+		Item::Mod(item) => Some(&item.ident),
+		Item::Struct(item) => Some(&item.ident),
+		Item::Enum(item) => Some(&item.ident),
+		Item::Union(item) => Some(&item.ident),
+		Item::Type(item) => Some(&item.ident),
+		Item::Trait(item) => Some(&item.ident),
+		_ => None,
+	}
+}
+
+pub fn generate_module(module: &ItemMod, args: &ModuleArgs) -> TokenStream {
+	let mut module = module.clone();
+	let mut errors = Vec::new();
+	let mut exports = Vec::new();
+
+	let name = match &args.name {
+		Some(name) => name.value(),
+		None => module.ident.to_string(),
+	};
+
+	let Some((_, items)) = &mut module.content else {
+		let err = Error::new(module.span(), "modules exported to Lua must be inline");
+		return err.to_compile_error();
+	};
+
+	for item in items.iter() {
+		if let Some(ident) = item_ident(item)
+			&& (ident == "table" || ident == "register")
+		{
+			let err = Error::new(
+				ident.span(),
+				format!(
+					"`{ident}` is reserved for the function generated by `#[flatgrass::module]`"
+				),
+			);
+			errors.push(err.to_compile_error());
+		}
+	}
+
+	for item in items.iter_mut() {
+		let (attrs, ident, value) = match item {
+			Item::Fn(func) if matches!(func.vis, Visibility::Public(_)) => {
+				if !func
+					.attrs
+					.iter()
+					.any(|attr| is_flatgrass_attr(attr, "function"))
+				{
+					continue;
+				} else if !func.sig.generics.params.is_empty() {
+					let err = Error::new(
+						func.sig.generics.span(),
+						"generic functions cannot be exported to Lua",
+					);
+					errors.push(err.to_compile_error());
+					continue;
+				}
+
+				let ident = &func.sig.ident;
+				let value =
+					quote! { ::flatgrass::lua::Function::new(::flatgrass::cfunction!(#ident)) };
+				(&mut func.attrs, ident.clone(), value)
+			}
+			Item::Const(item) if matches!(item.vis, Visibility::Public(_)) => {
+				let ident = &item.ident;
+				(&mut item.attrs, ident.clone(), quote! { #ident })
+			}
+			Item::Static(item) if matches!(item.vis, Visibility::Public(_)) => {
+				let ident = &item.ident;
+				(&mut item.attrs, ident.clone(), quote! { &#ident })
+			}
+			Item::Mod(item) if matches!(item.vis, Visibility::Public(_)) => {
+				if !item
+					.attrs
+					.iter()
+					.any(|attr| is_flatgrass_attr(attr, "module"))
+				{
+					continue;
+				}
+
+				let ident = &item.ident;
+				(&mut item.attrs, ident.clone(), quote! { #ident::table() })
+			}
+			_ => continue,
+		};
+
+		let key = match LuaAttrs::extract(attrs) {
+			Ok(LuaAttrs {
+				name: Some(name), ..
+			}) => name.value(),
+			Ok(_) => ident.to_string(),
+			Err(err) => {
+				errors.push(err.to_compile_error());
+				continue;
+			}
+		};
+
+		exports.push(quote! {
+			__fg_table.raw_set(#key, #value);
+		});
+	}
+
+	items.push(parse_quote! {
+		#[doc = "Creates a table containing the items exported to Lua by this module."]
+		pub fn table() -> ::flatgrass::lua::Table {
+			let __fg_table = ::flatgrass::lua::Table::new();
+			#(#exports)*
+			__fg_table
+		}
+	});
+
+	items.push(parse_quote! {
+		#[doc = ::core::concat!("Registers this module as the `", #name, "` global, and as the value returned by `require(\"", #name, "\")`.")]
+		pub fn register() -> ::flatgrass::lua::Table {
+			let __fg_table = table();
+			let __fg_globals = ::flatgrass::lua::Table::globals();
+			__fg_globals.raw_set(#name, &__fg_table);
+			if let ::flatgrass::lua::Value::Table(__fg_package) = __fg_globals.raw_get("package")
+				&& let ::flatgrass::lua::Value::Table(__fg_loaded) = __fg_package.raw_get("loaded")
+			{
+				__fg_loaded.raw_set(#name, &__fg_table);
+			}
+
+			__fg_table
+		}
+	});
+
+	quote! {
+		#module
+
+		#(#errors)*
+	}
+}
@@ -0,0 +1,120 @@
+use proc_macro2::*;
+use quote::quote;
+use syn::*;
+
+/// Parses the `#[net(bits = N)]` attribute of a field.
+fn field_bits(field: &Field) -> Result<Option<LitInt>> {
+	let mut bits = None;
+	for attr in field
+		.attrs
+		.iter()
+		.filter(|attr| attr.path().is_ident("net"))
+	{
+		attr.parse_nested_meta(|meta| {
+			if meta.path.is_ident("bits") {
+				let lit: LitInt = meta.value()?.parse()?;
+				match lit.base10_parse::<u32>()? {
+					1..=32 => bits = Some(lit),
+					_ => {
+						return Err(Error::new(
+							lit.span(),
+							"expected a number of bits between 1 and 32",
+						));
+					}
+				}
+				Ok(())
+			} else {
+				Err(meta.error("unsupported net attribute"))
+			}
+		})?;
+	}
+
+	Ok(bits)
+}
+
+/// Parses the `#[net(name = "...")]` attribute of a struct.
+fn message_name(input: &DeriveInput) -> Result<LitStr> {
+	let mut name = LitStr::new(&input.ident.to_string(), input.ident.span());
+	for attr in input
+		.attrs
+		.iter()
+		.filter(|attr| attr.path().is_ident("net"))
+	{
+		attr.parse_nested_meta(|meta| {
+			if meta.path.is_ident("name") {
+				name = meta.value()?.parse()?;
+				Ok(())
+			} else {
+				Err(meta.error("unsupported net attribute"))
+			}
+		})?;
+	}
+
+	Ok(name)
+}
+
+pub fn generate_net_message(input: &DeriveInput) -> TokenStream {
+	let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+	let ident = &input.ident;
+
+	let fields = match &input.data {
+		Data::Struct(data) => &data.fields,
+		_ => {
+			let err = Error::new(ident.span(), "NetMessage can only be derived for structs");
+			return err.to_compile_error();
+		}
+	};
+
+	let mut errors = Vec::new();
+	let name = message_name(input).unwrap_or_else(|err| {
+		errors.push(err.to_compile_error());
+		LitStr::new("", Span::call_site())
+	});
+
+	let mut writes = Vec::new();
+	let mut reads = Vec::new();
+	for (i, field) in fields.iter().enumerate() {
+		let bits = field_bits(field).unwrap_or_else(|err| {
+			errors.push(err.to_compile_error());
+			None
+		});
+
+		let member = match &field.ident {
+			Some(ident) => Member::Named(ident.clone()),
+			None => Member::Unnamed(Index::from(i)),
+		};
+
+		let (write, read) = match bits {
+			Some(bits) => (
+				quote! { ::flatgrass::gm::net::NetInt::write_bits(&self.#member, __fg_writer, #bits)?; },
+				quote! { ::flatgrass::gm::net::NetInt::read_bits(__fg_reader, #bits)? },
+			),
+			None => (
+				quote! { ::flatgrass::gm::net::NetField::write(&self.#member, __fg_writer)?; },
+				quote! { ::flatgrass::gm::net::NetField::read(__fg_reader)? },
+			),
+		};
+
+		writes.push(write);
+		reads.push(quote! { #member: #read });
+	}
+
+	quote! {
+		#(#errors)*
+
+		impl #impl_generics ::flatgrass::gm::net::NetMessage for #ident #type_generics #where_clause {
+			const NAME: &'static str = #name;
+
+			fn write(&self, __fg_writer: &mut ::flatgrass::gm::net::Writer) -> ::core::result::Result<(), ::flatgrass::lua::Value> {
+				#(#writes)*
+				::core::result::Result::Ok(())
+			}
+
+			fn read(__fg_reader: &mut ::flatgrass::gm::net::Reader) -> ::core::result::Result<Self, ::flatgrass::gm::net::NetError> {
+				::core::result::Result::Ok(Self {
+					#(#reads),*
+				})
+			}
+		}
+	}
+}
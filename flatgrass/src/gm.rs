@@ -2,11 +2,61 @@ use crate::prelude::*;
 
 #[doc(inline)]
 pub use crate::printfg;
+#[doc(inline)]
+pub use crate::{__fg_error as error, __fg_warn as warn};
 mod macros;
 
 mod functions;
 pub use functions::*;
 
+mod math;
+pub use math::*;
+
+mod console;
+pub use console::*;
+
+#[doc(inline)]
+pub use entity::{Entity, Player};
+
+pub mod concommand;
+pub mod convar;
+pub mod entity;
+pub mod file;
+pub mod hook;
+pub mod net;
+pub mod timer;
+pub mod util;
+
+/// Removes everything registered from this module.
+pub(crate) fn clear() {
+	hook::clear();
+	concommand::clear();
+	convar::clear();
+	net::clear();
+	timer::clear();
+}
+
+/// Gets a function from the global table, such as `CreateConVar`.
+fn global_function(name: &str) -> Result<Function, Value> {
+	match Table::globals().raw_get(name) {
+		Value::Function(func) => Ok(func),
+		_ => Err(format!("'{name}' is not available").to_lua()),
+	}
+}
+
+/// Gets a function from a library in the global table, such as `hook.Add`.
+pub(crate) fn library_function(library: &str, name: &str) -> Result<Function, Value> {
+	let func = match Table::globals().raw_get(library) {
+		Value::Table(library) => library.raw_get(name),
+		_ => Value::Nil,
+	};
+
+	match func {
+		Value::Function(func) => Ok(func),
+		_ => Err(format!("'{library}.{name}' is not available").to_lua()),
+	}
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Realm {
 	Server,
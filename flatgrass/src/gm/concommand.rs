@@ -0,0 +1,85 @@
+use super::{Entity, Player, library_function};
+use crate::prelude::*;
+use std::cell::RefCell;
+
+thread_local! {
+	static COMMANDS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Collects the strings of a sequential table.
+fn strings(table: &Table) -> Vec<String> {
+	table
+		.ipairs()
+		.map(|(_, value)| match value {
+			Value::String(lstr) => lstr.to_string(),
+			value => format!("{value:?}"),
+		})
+		.collect()
+}
+
+/// Adds a console command using [`concommand.Add`](https://wiki.facepunch.com/gmod/concommand.Add).\
+/// The callback receives the player who ran the command, which is `None` for the server console,
+/// the name of the command, its arguments and the string they were parsed from.\
+/// Commands are removed automatically when the module exits.
+pub fn add<F>(name: &str, func: F) -> Result<(), Value>
+where
+	F: Fn(Option<Player>, String, Vec<String>, String) + 'static,
+{
+	let func = Function::from_fn(move |(ply, cmd, args, arg_str): (Entity, _, Table, _)| {
+		func(ply.to_player(), cmd, strings(&args), arg_str)
+	});
+
+	add_raw(name, func, Value::Nil)
+}
+
+/// Adds a console command with an autocompletion callback.\
+/// The autocompletion callback receives the name of the command, the string of arguments
+/// and the arguments, and returns the suggestions to display.
+pub fn add_with_autocomplete<F, C>(name: &str, func: F, autocomplete: C) -> Result<(), Value>
+where
+	F: Fn(Option<Player>, String, Vec<String>, String) + 'static,
+	C: Fn(String, String, Vec<String>) -> Vec<String> + 'static,
+{
+	let func = Function::from_fn(move |(ply, cmd, args, arg_str): (Entity, _, Table, _)| {
+		func(ply.to_player(), cmd, strings(&args), arg_str)
+	});
+
+	let autocomplete = Function::from_fn(move |(cmd, arg_str, args): (_, _, Option<Table>)| {
+		let args = args.as_ref().map(strings).unwrap_or_default();
+		autocomplete(cmd, arg_str, args)
+	});
+
+	add_raw(name, func, autocomplete.to_lua())
+}
+
+fn add_raw(name: &str, func: Function, autocomplete: Value) -> Result<(), Value> {
+	let add = library_function("concommand", "Add")?;
+	call!(add: name, func, autocomplete)?;
+	COMMANDS.with_borrow_mut(|commands| {
+		commands.retain(|command| command != name);
+		commands.push(name.to_string());
+	});
+
+	Ok(())
+}
+
+/// Removes a console command using [`concommand.Remove`](https://wiki.facepunch.com/gmod/concommand.Remove).
+pub fn remove(name: &str) -> Result<(), Value> {
+	let remove = library_function("concommand", "Remove")?;
+	call!(remove: name)?;
+	COMMANDS.with_borrow_mut(|commands| {
+		commands.retain(|command| command != name);
+	});
+
+	Ok(())
+}
+
+/// Removes every console command added from this module.
+pub(crate) fn clear() {
+	let commands = COMMANDS.take();
+	if let Ok(remove) = library_function("concommand", "Remove") {
+		for name in commands {
+			let _ = call!(remove: name);
+		}
+	}
+}
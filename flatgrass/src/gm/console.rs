@@ -0,0 +1,321 @@
+use super::*;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+/// The color of text printed without a specific color.
+const TEXT_COLOR: Color = Color::rgb(230, 230, 230);
+
+/// The severity of a message printed to the console.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Level {
+	Error,
+	Warn,
+	Info,
+	Debug,
+	Trace,
+}
+
+impl Level {
+	pub const fn name(self) -> &'static str {
+		match self {
+			Self::Error => "ERROR",
+			Self::Warn => "WARN",
+			Self::Info => "INFO",
+			Self::Debug => "DEBUG",
+			Self::Trace => "TRACE",
+		}
+	}
+
+	/// The color used to print the level.
+	pub const fn color(self) -> Color {
+		match self {
+			Self::Error => Color::rgb(255, 90, 90),
+			Self::Warn => Color::rgb(255, 200, 60),
+			Self::Info => Color::rgb(110, 200, 255),
+			Self::Debug => Color::rgb(180, 180, 180),
+			Self::Trace => Color::rgb(130, 130, 130),
+		}
+	}
+}
+
+impl Realm {
+	pub const fn name(self) -> &'static str {
+		match self {
+			Self::Server => "server",
+			Self::Client => "client",
+			Self::Menu => "menu",
+		}
+	}
+
+	/// The color used by the game to tell realms apart.
+	pub const fn color(self) -> Color {
+		match self {
+			Self::Server => Color::rgb(137, 222, 255),
+			Self::Client => Color::rgb(255, 222, 102),
+			Self::Menu => Color::rgb(120, 220, 120),
+		}
+	}
+}
+
+/// Messages printed from other threads, waiting to be printed on the Lua thread.
+static PENDING: Mutex<Vec<Vec<(Color, String)>>> = Mutex::new(Vec::new());
+
+fn pending() -> MutexGuard<'static, Vec<Vec<(Color, String)>>> {
+	PENDING.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Prints the messages buffered from other threads.\
+/// This happens on every tick of the async runtime, and before anything else is printed from the Lua thread.
+pub(crate) fn flush_pending() {
+	let pending = std::mem::take(&mut *pending());
+	for parts in pending {
+		print_colored(parts.iter().map(|(color, text)| (*color, text.as_str())));
+	}
+}
+
+/// Prints colored text to the console using [`MsgC`](https://wiki.facepunch.com/gmod/Global.MsgC).\
+/// No newline is added, and the text is printed with [`print`] if `MsgC` isn't available.
+///
+/// Text printed from other threads is buffered and printed later from the Lua thread.
+pub fn msgc<'a>(parts: impl IntoIterator<Item = (Color, &'a str)>) {
+	if Lua::is_valid() {
+		flush_pending();
+		print_colored(parts);
+	} else {
+		let parts = parts.into_iter();
+		let parts = parts.map(|(color, text)| (color, text.to_string()));
+		pending().push(parts.collect());
+	}
+}
+
+fn print_colored<'a>(parts: impl IntoIterator<Item = (Color, &'a str)>) {
+	let parts = parts.into_iter();
+	match global_function("MsgC") {
+		Ok(msgc) => {
+			let args = parts.flat_map(|(color, text)| [color.to_lua(), text.to_lua()]);
+			let _ = msgc.call(args);
+		}
+		Err(_) => {
+			let text = parts.map(|(_, text)| text).collect::<String>();
+			print(text.trim_end_matches('\n'));
+		}
+	}
+}
+
+/// Prints a message prefixed with its level and the module it comes from.
+pub fn log(level: Level, module: &str, msg: &str) {
+	msgc([
+		(level.color(), &*format!("[{}] ", level.name())),
+		(level.color(), &*format!("{module}: ")),
+		(TEXT_COLOR, msg),
+		(TEXT_COLOR, "\n"),
+	]);
+}
+
+/// A [`log`] backend printing to the console, with the realm it was created in.
+///
+/// Messages logged from other threads are buffered and printed later from the Lua thread, see [`msgc`].
+#[cfg(feature = "log")]
+#[derive(Debug, Clone)]
+pub struct Logger {
+	realm: Option<Realm>,
+	level: ::log::LevelFilter,
+}
+
+#[cfg(feature = "log")]
+impl Logger {
+	pub fn new() -> Self {
+		Self {
+			realm: Realm::get(),
+			level: ::log::LevelFilter::Info,
+		}
+	}
+
+	/// Sets the maximum level of the messages to print.
+	pub fn with_level(mut self, level: ::log::LevelFilter) -> Self {
+		self.level = level;
+		self
+	}
+
+	/// Sets this logger as the global logger.
+	pub fn init(self) -> Result<(), ::log::SetLoggerError> {
+		let level = self.level;
+		::log::set_boxed_logger(Box::new(self))?;
+		::log::set_max_level(level);
+		Ok(())
+	}
+}
+
+#[cfg(feature = "log")]
+impl Default for Logger {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[cfg(feature = "log")]
+impl ::log::Log for Logger {
+	fn enabled(&self, metadata: &::log::Metadata) -> bool {
+		metadata.level() <= self.level
+	}
+
+	fn log(&self, record: &::log::Record) {
+		if self.enabled(record.metadata()) {
+			let level = match record.level() {
+				::log::Level::Error => Level::Error,
+				::log::Level::Warn => Level::Warn,
+				::log::Level::Info => Level::Info,
+				::log::Level::Debug => Level::Debug,
+				::log::Level::Trace => Level::Trace,
+			};
+
+			let msg = record.args().to_string();
+			let realm = self
+				.realm
+				.map(|realm| (realm.color(), format!("[{}] ", realm.name())));
+			let realm = realm.as_ref().map(|(color, tag)| (*color, tag.as_str()));
+			msgc(realm.into_iter().chain([
+				(level.color(), &*format!("[{}] ", level.name())),
+				(level.color(), &*format!("{}: ", record.target())),
+				(TEXT_COLOR, &*msg),
+				(TEXT_COLOR, "\n"),
+			]));
+		}
+	}
+
+	fn flush(&self) {}
+}
+
+/// A [`tracing_subscriber`] layer printing events and the spans they happen in to the console.
+///
+/// Events recorded on other threads, such as in [`spawn_blocking`](crate::task::spawn_blocking),
+/// are buffered and printed on the next tick.
+#[cfg(feature = "tracing")]
+#[derive(Debug, Clone)]
+pub struct ConsoleLayer {
+	realm: Option<Realm>,
+}
+
+#[cfg(feature = "tracing")]
+impl ConsoleLayer {
+	pub fn new() -> Self {
+		Self {
+			realm: Realm::get(),
+		}
+	}
+}
+
+#[cfg(feature = "tracing")]
+impl Default for ConsoleLayer {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// The formatted fields of a span, stored in its extensions.
+#[cfg(feature = "tracing")]
+struct SpanFields(String);
+
+/// Formats fields as `name=value`, keeping the message apart.
+#[cfg(feature = "tracing")]
+#[derive(Default)]
+struct FieldVisitor {
+	message: String,
+	fields: String,
+}
+
+#[cfg(feature = "tracing")]
+impl ::tracing::field::Visit for FieldVisitor {
+	fn record_str(&mut self, field: &::tracing::field::Field, value: &str) {
+		self.record_debug(field, &format_args!("{value}"));
+	}
+
+	fn record_debug(&mut self, field: &::tracing::field::Field, value: &dyn std::fmt::Debug) {
+		use std::fmt::Write;
+		if field.name() == "message" {
+			let _ = write!(self.message, "{value:?}");
+		} else {
+			if !self.fields.is_empty() {
+				self.fields.push(' ');
+			}
+
+			let _ = write!(self.fields, "{}={value:?}", field.name());
+		}
+	}
+}
+
+#[cfg(feature = "tracing")]
+impl<S> ::tracing_subscriber::Layer<S> for ConsoleLayer
+where
+	S: ::tracing::Subscriber + for<'a> ::tracing_subscriber::registry::LookupSpan<'a>,
+{
+	fn on_new_span(
+		&self,
+		attrs: &::tracing::span::Attributes,
+		id: &::tracing::span::Id,
+		ctx: ::tracing_subscriber::layer::Context<S>,
+	) {
+		if let Some(span) = ctx.span(id) {
+			let mut visitor = FieldVisitor::default();
+			attrs.record(&mut visitor);
+			span.extensions_mut().insert(SpanFields(visitor.fields));
+		}
+	}
+
+	fn on_record(
+		&self,
+		id: &::tracing::span::Id,
+		values: &::tracing::span::Record,
+		ctx: ::tracing_subscriber::layer::Context<S>,
+	) {
+		if let Some(span) = ctx.span(id) {
+			let mut extensions = span.extensions_mut();
+			if let Some(SpanFields(fields)) = extensions.get_mut::<SpanFields>() {
+				let mut visitor = FieldVisitor {
+					fields: std::mem::take(fields),
+					..Default::default()
+				};
+
+				values.record(&mut visitor);
+				*fields = visitor.fields;
+			}
+		}
+	}
+
+	fn on_event(&self, event: &::tracing::Event, ctx: ::tracing_subscriber::layer::Context<S>) {
+		use std::fmt::Write;
+		let metadata = event.metadata();
+		let mut line = String::new();
+		if let Some(realm) = self.realm {
+			let _ = write!(line, "[{}] ", realm.name());
+		}
+
+		let _ = write!(line, "[{}] {}: ", metadata.level(), metadata.target());
+		if let Some(scope) = ctx.event_scope(event) {
+			for span in scope.from_root() {
+				line.push_str(span.name());
+				if let Some(SpanFields(fields)) = span.extensions().get::<SpanFields>()
+					&& !fields.is_empty()
+				{
+					let _ = write!(line, "{{{fields}}}");
+				}
+
+				line.push_str(": ");
+			}
+		}
+
+		let mut visitor = FieldVisitor::default();
+		event.record(&mut visitor);
+		line.push_str(&visitor.message);
+		if !visitor.fields.is_empty() {
+			let _ = write!(line, " {}", visitor.fields);
+		}
+
+		if Lua::is_valid() {
+			flush_pending();
+			print(&line);
+		} else {
+			line.push('\n');
+			pending().push(vec![(TEXT_COLOR, line)]);
+		}
+	}
+}
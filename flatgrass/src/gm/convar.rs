@@ -0,0 +1,227 @@
+use super::{global_function, library_function};
+use crate::lua::error::FromLuaError;
+use crate::prelude::*;
+use bitflags::bitflags;
+use std::cell::RefCell;
+
+thread_local! {
+	static CALLBACKS: RefCell<Vec<(String, String)>> = const { RefCell::new(Vec::new()) };
+}
+
+bitflags! {
+	/// See [FCVAR](https://wiki.facepunch.com/gmod/Enums/FCVAR).
+	#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+	pub struct Flags: u32 {
+		const UNREGISTERED = 1 << 0;
+		const DEVELOPMENT_ONLY = 1 << 1;
+		const GAMEDLL = 1 << 2;
+		const CLIENTDLL = 1 << 3;
+		const HIDDEN = 1 << 4;
+		const PROTECTED = 1 << 5;
+		const SPONLY = 1 << 6;
+		const ARCHIVE = 1 << 7;
+		const NOTIFY = 1 << 8;
+		const USERINFO = 1 << 9;
+		const PRINTABLE_ONLY = 1 << 10;
+		const UNLOGGED = 1 << 11;
+		const NEVER_AS_STRING = 1 << 12;
+		const REPLICATED = 1 << 13;
+		const CHEAT = 1 << 14;
+		const SS = 1 << 15;
+		const DEMO = 1 << 16;
+		const DONT_RECORD = 1 << 17;
+		const LUA_CLIENT = 1 << 18;
+		const LUA_SERVER = 1 << 19;
+		const NOT_CONNECTED = 1 << 22;
+		const ARCHIVE_XBOX = 1 << 24;
+		const SERVER_CAN_EXECUTE = 1 << 28;
+		const SERVER_CANNOT_QUERY = 1 << 29;
+		const CLIENTCMD_CAN_EXECUTE = 1 << 30;
+	}
+}
+
+impl ToLua for Flags {
+	fn to_lua_by_ref(&self) -> Value {
+		self.bits().to_lua()
+	}
+}
+
+/// A console variable, see [ConVar](https://wiki.facepunch.com/gmod/ConVar).
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub struct ConVar {
+	udata: Userdata,
+}
+
+impl ConVar {
+	/// Creates a console variable using [`CreateConVar`](https://wiki.facepunch.com/gmod/Global.CreateConVar),
+	/// or returns the existing one with that name.
+	pub fn create(name: &str, default: &str, flags: Flags, help: &str) -> Result<Self, Value> {
+		let create = global_function("CreateConVar")?;
+		create.call_as([
+			name.to_lua(),
+			default.to_lua(),
+			flags.to_lua(),
+			help.to_lua(),
+		])
+	}
+
+	/// Creates a numeric console variable clamped between `min` and `max`.
+	pub fn create_ranged(
+		name: &str,
+		default: f64,
+		flags: Flags,
+		help: &str,
+		min: f64,
+		max: f64,
+	) -> Result<Self, Value> {
+		let create = global_function("CreateConVar")?;
+		create.call_as([
+			name.to_lua(),
+			default.to_lua(),
+			flags.to_lua(),
+			help.to_lua(),
+			min.to_lua(),
+			max.to_lua(),
+		])
+	}
+
+	/// Finds an existing console variable using [`GetConVar`](https://wiki.facepunch.com/gmod/Global.GetConVar).
+	pub fn find(name: &str) -> Option<Self> {
+		let get = global_function("GetConVar").ok()?;
+		get.call_as::<Option<Self>, _>([name]).ok().flatten()
+	}
+
+	pub fn name(&self) -> Result<String, Value> {
+		self.udata.call_method_as("GetName", [] as [Value; 0])
+	}
+
+	pub fn help_text(&self) -> Result<String, Value> {
+		self.udata.call_method_as("GetHelpText", [] as [Value; 0])
+	}
+
+	pub fn default(&self) -> Result<String, Value> {
+		self.udata.call_method_as("GetDefault", [] as [Value; 0])
+	}
+
+	pub fn flags(&self) -> Result<Flags, Value> {
+		let flags: f64 = self.udata.call_method_as("GetFlags", [] as [Value; 0])?;
+		Ok(Flags::from_bits_retain(flags as u32))
+	}
+
+	pub fn get_int(&self) -> Result<i32, Value> {
+		let value: f64 = self.udata.call_method_as("GetInt", [] as [Value; 0])?;
+		Ok(value as i32)
+	}
+
+	pub fn get_float(&self) -> Result<f64, Value> {
+		self.udata.call_method_as("GetFloat", [] as [Value; 0])
+	}
+
+	pub fn get_bool(&self) -> Result<bool, Value> {
+		self.udata.call_method_as("GetBool", [] as [Value; 0])
+	}
+
+	pub fn get_string(&self) -> Result<String, Value> {
+		self.udata.call_method_as("GetString", [] as [Value; 0])
+	}
+
+	pub fn set_int(&self, value: i32) -> Result<(), Value> {
+		self.udata.call_method("SetInt", [value]).map(drop)
+	}
+
+	pub fn set_float(&self, value: f64) -> Result<(), Value> {
+		self.udata.call_method("SetFloat", [value]).map(drop)
+	}
+
+	pub fn set_bool(&self, value: bool) -> Result<(), Value> {
+		self.udata.call_method("SetBool", [value]).map(drop)
+	}
+
+	pub fn set_string(&self, value: &str) -> Result<(), Value> {
+		self.udata.call_method("SetString", [value]).map(drop)
+	}
+
+	/// Calls `func` with the name, old value and new value of the console variable when it changes,
+	/// using [`cvars.AddChangeCallback`](https://wiki.facepunch.com/gmod/cvars.AddChangeCallback).\
+	/// Callbacks are removed automatically when the module exits.
+	pub fn on_change<F>(&self, identifier: &str, func: F) -> Result<(), Value>
+	where
+		F: Fn(String, String, String) + 'static,
+	{
+		let add = library_function("cvars", "AddChangeCallback")?;
+		let name = self.name()?;
+		let func = Function::from_fn(move |(name, old, new)| func(name, old, new));
+		call!(add: &name, func, identifier)?;
+		CALLBACKS.with_borrow_mut(|callbacks| {
+			callbacks.retain(|(n, id)| *n != name || id != identifier);
+			callbacks.push((name, identifier.to_string()));
+		});
+
+		Ok(())
+	}
+
+	/// Removes a callback added with [`on_change`](Self::on_change).
+	pub fn remove_on_change(&self, identifier: &str) -> Result<(), Value> {
+		let remove = library_function("cvars", "RemoveChangeCallback")?;
+		let name = self.name()?;
+		call!(remove: &name, identifier)?;
+		CALLBACKS.with_borrow_mut(|callbacks| {
+			callbacks.retain(|(n, id)| *n != name || id != identifier);
+		});
+
+		Ok(())
+	}
+
+	pub fn to_userdata(&self) -> &Userdata {
+		&self.udata
+	}
+}
+
+impl ToLua for ConVar {
+	fn to_lua_by_ref(&self) -> Value {
+		self.udata.to_lua_by_ref()
+	}
+
+	fn to_lua(self) -> Value {
+		self.udata.to_lua()
+	}
+}
+
+/// Checks if a userdata is a convar, comparing its metatable with [`FindMetaTable("ConVar")`](https://wiki.facepunch.com/gmod/Global.FindMetaTable).
+fn is_convar(udata: &Userdata) -> bool {
+	let metatable =
+		global_function("getmetatable").and_then(|getmetatable| getmetatable.call_as([udata]));
+	let convar = global_function("FindMetaTable").and_then(|find| find.call_as(["ConVar"]));
+	match (metatable, convar) {
+		(Ok(Value::Table(metatable)), Ok(Value::Table(convar))) => metatable == convar,
+		_ => false,
+	}
+}
+
+impl FromLua for ConVar {
+	type Err = FromLuaError<'static>;
+
+	fn from_lua(value: Value) -> Result<Self, Self::Err> {
+		match value {
+			Value::Userdata(udata) if is_convar(&udata) => Ok(Self { udata }),
+			value => Err(FromLuaError::expected_and_got(
+				"ConVar",
+				value.get_type().name(),
+			)),
+		}
+	}
+
+	fn no_value() -> Result<Self, Self::Err> {
+		Err(FromLuaError::expected("ConVar"))
+	}
+}
+
+/// Removes every change callback added from this module.
+pub(crate) fn clear() {
+	let callbacks = CALLBACKS.take();
+	if let Ok(remove) = library_function("cvars", "RemoveChangeCallback") {
+		for (name, identifier) in callbacks {
+			let _ = call!(remove: name, identifier);
+		}
+	}
+}
@@ -0,0 +1,373 @@
+use super::{Angle, Vector, global_function, library_function};
+use crate::lua::FromLuaMulti;
+use crate::lua::error::FromLuaError;
+use crate::prelude::*;
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::marker::PhantomData;
+use std::ops::Deref;
+
+const NO_ARGS: [Value; 0] = [];
+
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub enum EntityError {
+	/// The entity is `NULL` or has been removed.
+	Invalid,
+	/// An error raised by Lua.
+	Lua(Value),
+}
+
+impl Error for EntityError {}
+impl Display for EntityError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::Invalid => write!(f, "attempted to use a NULL entity"),
+			Self::Lua(Value::String(lstr)) => write!(f, "{lstr}"),
+			Self::Lua(value) => write!(f, "{value:?}"),
+		}
+	}
+}
+
+impl ToLua for EntityError {
+	fn to_lua_by_ref(&self) -> Value {
+		match self {
+			Self::Lua(value) => value.clone(),
+			_ => self.to_string().to_lua(),
+		}
+	}
+}
+
+impl From<Value> for EntityError {
+	fn from(value: Value) -> Self {
+		Self::Lua(value)
+	}
+}
+
+impl From<EntityError> for Value {
+	fn from(err: EntityError) -> Self {
+		err.to_lua()
+	}
+}
+
+/// A handle to an entity, see [Entity](https://wiki.facepunch.com/gmod/Entity).
+///
+/// The handle may outlive the entity, in which case its methods return [`EntityError::Invalid`].
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub struct Entity {
+	udata: Userdata,
+}
+
+impl Entity {
+	/// Gets an entity by its index using [`Entity`](https://wiki.facepunch.com/gmod/Global.Entity).
+	pub fn by_index(index: i32) -> Option<Self> {
+		let entity = global_function("Entity").ok()?;
+		let entity: Self = entity.call_as([index]).ok()?;
+		entity.is_valid().then_some(entity)
+	}
+
+	/// Returns whether the entity still exists. The world is considered valid.
+	pub fn is_valid(&self) -> bool {
+		let check = |name| self.udata.call_method_as(name, NO_ARGS).unwrap_or(false);
+		check("IsValid") || check("IsWorld")
+	}
+
+	/// Calls a method of the entity if it is valid.
+	pub fn call_method<R, T>(&self, name: &str, args: T) -> Result<R, EntityError>
+	where
+		R: FromLuaMulti,
+		T: IntoIterator<Item: ToLua>,
+	{
+		match self.is_valid() {
+			true => Ok(self.udata.call_method_as(name, args)?),
+			false => Err(EntityError::Invalid),
+		}
+	}
+
+	/// Calls a method of the entity returning an integer.
+	fn call_int(&self, name: &str) -> Result<i32, EntityError> {
+		self.call_method(name, NO_ARGS).map(|num: f64| num as i32)
+	}
+
+	pub fn index(&self) -> Result<i32, EntityError> {
+		self.call_int("EntIndex")
+	}
+
+	pub fn class(&self) -> Result<String, EntityError> {
+		self.call_method("GetClass", NO_ARGS)
+	}
+
+	pub fn model(&self) -> Result<Option<String>, EntityError> {
+		self.call_method("GetModel", NO_ARGS)
+	}
+
+	pub fn pos(&self) -> Result<Vector, EntityError> {
+		self.call_method("GetPos", NO_ARGS)
+	}
+
+	pub fn set_pos(&self, pos: Vector) -> Result<(), EntityError> {
+		self.call_method("SetPos", [pos])
+	}
+
+	pub fn angles(&self) -> Result<Angle, EntityError> {
+		self.call_method("GetAngles", NO_ARGS)
+	}
+
+	pub fn set_angles(&self, angles: Angle) -> Result<(), EntityError> {
+		self.call_method("SetAngles", [angles])
+	}
+
+	pub fn velocity(&self) -> Result<Vector, EntityError> {
+		self.call_method("GetVelocity", NO_ARGS)
+	}
+
+	pub fn health(&self) -> Result<i32, EntityError> {
+		self.call_int("Health")
+	}
+
+	pub fn max_health(&self) -> Result<i32, EntityError> {
+		self.call_int("GetMaxHealth")
+	}
+
+	pub fn set_health(&self, health: i32) -> Result<(), EntityError> {
+		self.call_method("SetHealth", [health])
+	}
+
+	/// The owner of the entity, if it has one.
+	pub fn owner(&self) -> Result<Option<Self>, EntityError> {
+		let owner: Self = self.call_method("GetOwner", NO_ARGS)?;
+		Ok(owner.is_valid().then_some(owner))
+	}
+
+	pub fn is_player(&self) -> bool {
+		self.call_method("IsPlayer", NO_ARGS).unwrap_or(false)
+	}
+
+	/// Returns the entity as a player if it is one.
+	pub fn to_player(&self) -> Option<Player> {
+		match self.is_player() {
+			true => Some(Player {
+				entity: self.clone(),
+			}),
+			false => None,
+		}
+	}
+
+	/// Removes the entity using [`Entity:Remove`](https://wiki.facepunch.com/gmod/Entity:Remove).
+	pub fn remove(&self) -> Result<(), EntityError> {
+		self.call_method("Remove", NO_ARGS)
+	}
+
+	pub fn to_userdata(&self) -> &Userdata {
+		&self.udata
+	}
+}
+
+impl ToLua for Entity {
+	fn to_lua_by_ref(&self) -> Value {
+		self.udata.to_lua_by_ref()
+	}
+
+	fn to_lua(self) -> Value {
+		self.udata.to_lua()
+	}
+}
+
+/// Checks if a userdata is an entity using [`isentity`](https://wiki.facepunch.com/gmod/Global.isentity).\
+/// `NULL` is an entity, so handles to removed entities are accepted.
+fn is_entity(udata: &Userdata) -> bool {
+	global_function("isentity")
+		.and_then(|isentity| isentity.call_as([udata]))
+		.unwrap_or(false)
+}
+
+impl FromLua for Entity {
+	type Err = FromLuaError<'static>;
+
+	fn from_lua(value: Value) -> Result<Self, Self::Err> {
+		match value {
+			Value::Userdata(udata) if is_entity(&udata) => Ok(Self { udata }),
+			value => Err(FromLuaError::expected_and_got(
+				"Entity",
+				value.get_type().name(),
+			)),
+		}
+	}
+
+	fn no_value() -> Result<Self, Self::Err> {
+		Err(FromLuaError::expected("Entity"))
+	}
+}
+
+/// A handle to a player, see [Player](https://wiki.facepunch.com/gmod/Player).
+///
+/// Players are entities, so this dereferences to [`Entity`].
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub struct Player {
+	entity: Entity,
+}
+
+impl Player {
+	/// Gets a player by their user ID using [`Player`](https://wiki.facepunch.com/gmod/Global.Player).
+	pub fn by_user_id(id: i32) -> Option<Self> {
+		let player = global_function("Player").ok()?;
+		let entity: Entity = player.call_as([id]).ok()?;
+		entity.to_player()
+	}
+
+	pub fn nick(&self) -> Result<String, EntityError> {
+		self.call_method("Nick", NO_ARGS)
+	}
+
+	pub fn steam_id(&self) -> Result<String, EntityError> {
+		self.call_method("SteamID", NO_ARGS)
+	}
+
+	pub fn steam_id64(&self) -> Result<u64, EntityError> {
+		let id: String = self.call_method("SteamID64", NO_ARGS)?;
+		id.parse()
+			.map_err(|_| EntityError::Lua(format!("invalid SteamID64 '{id}'").to_lua()))
+	}
+
+	pub fn user_id(&self) -> Result<i32, EntityError> {
+		self.call_int("UserID")
+	}
+
+	pub fn armor(&self) -> Result<i32, EntityError> {
+		self.call_int("Armor")
+	}
+
+	pub fn set_armor(&self, armor: i32) -> Result<(), EntityError> {
+		self.call_method("SetArmor", [armor])
+	}
+
+	pub fn alive(&self) -> Result<bool, EntityError> {
+		self.call_method("Alive", NO_ARGS)
+	}
+
+	pub fn is_bot(&self) -> Result<bool, EntityError> {
+		self.call_method("IsBot", NO_ARGS)
+	}
+
+	pub fn is_admin(&self) -> Result<bool, EntityError> {
+		self.call_method("IsAdmin", NO_ARGS)
+	}
+
+	pub fn is_super_admin(&self) -> Result<bool, EntityError> {
+		self.call_method("IsSuperAdmin", NO_ARGS)
+	}
+
+	pub fn ping(&self) -> Result<i32, EntityError> {
+		self.call_int("Ping")
+	}
+
+	pub fn kill(&self) -> Result<(), EntityError> {
+		self.call_method("Kill", NO_ARGS)
+	}
+
+	pub fn chat_print(&self, msg: &str) -> Result<(), EntityError> {
+		self.call_method("ChatPrint", [msg])
+	}
+
+	pub fn kick(&self, reason: &str) -> Result<(), EntityError> {
+		self.call_method("Kick", [reason])
+	}
+}
+
+impl Deref for Player {
+	type Target = Entity;
+
+	fn deref(&self) -> &Entity {
+		&self.entity
+	}
+}
+
+impl From<Player> for Entity {
+	fn from(player: Player) -> Self {
+		player.entity
+	}
+}
+
+impl ToLua for Player {
+	fn to_lua_by_ref(&self) -> Value {
+		self.entity.to_lua_by_ref()
+	}
+
+	fn to_lua(self) -> Value {
+		self.entity.to_lua()
+	}
+}
+
+impl FromLua for Player {
+	type Err = FromLuaError<'static>;
+
+	fn from_lua(value: Value) -> Result<Self, Self::Err> {
+		let ty = value.get_type().name();
+		match Entity::from_lua(value).map(|entity| entity.to_player()) {
+			Ok(Some(player)) => Ok(player),
+			_ => Err(FromLuaError::expected_and_got("Player", ty)),
+		}
+	}
+
+	fn no_value() -> Result<Self, Self::Err> {
+		Err(FromLuaError::expected("Player"))
+	}
+}
+
+/// An iterator over a list of entities, skipping those that aren't of type `T`.
+#[derive(Debug, Clone)]
+pub struct Entities<T = Entity> {
+	tbl: Table,
+	idx: usize,
+	len: usize,
+	_phantom: PhantomData<T>,
+}
+
+impl<T> Entities<T> {
+	fn new(tbl: Table) -> Self {
+		let len = tbl.len();
+		Self {
+			tbl,
+			idx: 0,
+			len,
+			_phantom: PhantomData,
+		}
+	}
+}
+
+impl<T: FromLua> Iterator for Entities<T> {
+	type Item = T;
+
+	fn next(&mut self) -> Option<T> {
+		while self.idx < self.len {
+			self.idx += 1;
+			if let Ok(entity) = T::from_lua(self.tbl.raw_get(self.idx)) {
+				return Some(entity);
+			}
+		}
+
+		None
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		(0, Some(self.len - self.idx))
+	}
+}
+
+/// Iterates over every entity using [`ents.GetAll`](https://wiki.facepunch.com/gmod/ents.GetAll).
+pub fn all() -> Result<Entities, Value> {
+	let get_all = library_function("ents", "GetAll")?;
+	get_all.call_as(NO_ARGS).map(Entities::new)
+}
+
+/// Iterates over the entities of a class using [`ents.FindByClass`](https://wiki.facepunch.com/gmod/ents.FindByClass).\
+/// The class can contain wildcards, such as `prop_*`.
+pub fn find_by_class(class: &str) -> Result<Entities, Value> {
+	let find = library_function("ents", "FindByClass")?;
+	find.call_as([class]).map(Entities::new)
+}
+
+/// Iterates over every player using [`player.GetAll`](https://wiki.facepunch.com/gmod/player.GetAll).
+pub fn players() -> Result<Entities<Player>, Value> {
+	let get_all = library_function("player", "GetAll")?;
+	get_all.call_as(NO_ARGS).map(Entities::new)
+}
@@ -0,0 +1,244 @@
+use super::library_function;
+use crate::lua::{FromLuaMulti, LuaString};
+use crate::prelude::*;
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::path::{Component, Path};
+use std::time::{Duration, SystemTime};
+
+/// The extensions allowed by [`file.Write`](https://wiki.facepunch.com/gmod/file.Write).
+const WRITABLE_EXTENSIONS: &[&str] = &[
+	"txt", "dat", "json", "xml", "csv", "dem", "vcd", "vtf", "vmt", "png", "jpg", "jpeg", "mp3",
+	"wav", "ogg",
+];
+
+/// Where to look for files, see [File Search Paths](https://wiki.facepunch.com/gmod/File_Search_Paths).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SearchPath {
+	/// Every mounted game, addon and the `garrysmod` folder.
+	Game,
+	/// Lua files available to the current realm.
+	Lua,
+	/// Lua files of the server.
+	LuaServer,
+	/// Lua files of the client.
+	LuaClient,
+	/// Lua files of the menu.
+	LuaMenu,
+	/// The `garrysmod/data` folder, the only one that can be written to.
+	Data,
+	/// The `garrysmod/download` folder.
+	Download,
+	/// The `garrysmod` folder.
+	Mod,
+	/// The folder containing the game executable.
+	BasePath,
+	/// Mounted workshop addons.
+	Workshop,
+	/// The map being played.
+	Bsp,
+	/// A mounted game, such as `cstrike`.
+	Mount(&'static str),
+}
+
+impl SearchPath {
+	pub const fn as_str(self) -> &'static str {
+		match self {
+			Self::Game => "GAME",
+			Self::Lua => "LUA",
+			Self::LuaServer => "lsv",
+			Self::LuaClient => "lcl",
+			Self::LuaMenu => "LuaMenu",
+			Self::Data => "DATA",
+			Self::Download => "DOWNLOAD",
+			Self::Mod => "MOD",
+			Self::BasePath => "BASE_PATH",
+			Self::Workshop => "WORKSHOP",
+			Self::Bsp => "BSP",
+			Self::Mount(name) => name,
+		}
+	}
+}
+
+impl Display for SearchPath {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}", self.as_str())
+	}
+}
+
+impl ToLua for SearchPath {
+	fn to_lua_by_ref(&self) -> Value {
+		self.as_str().to_lua()
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub enum FileError {
+	/// The file doesn't exist in the search path.
+	NotFound(String, SearchPath),
+	/// The path points outside of the `DATA` folder.
+	OutsideData(String),
+	/// The extension of the file can't be written to.
+	ForbiddenExtension(String),
+	/// The file doesn't contain valid UTF-8.
+	InvalidUtf8(String),
+	/// An error raised by Lua.
+	Lua(Value),
+}
+
+impl Error for FileError {}
+impl Display for FileError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::NotFound(path, search) => write!(f, "file '{path}' not found in {search}"),
+			Self::OutsideData(path) => write!(f, "cannot write to '{path}' outside of DATA"),
+			Self::ForbiddenExtension(path) => {
+				write!(f, "cannot write to '{path}' with this extension")
+			}
+			Self::InvalidUtf8(path) => write!(f, "file '{path}' is not valid UTF-8"),
+			Self::Lua(Value::String(lstr)) => write!(f, "{lstr}"),
+			Self::Lua(value) => write!(f, "{value:?}"),
+		}
+	}
+}
+
+impl ToLua for FileError {
+	fn to_lua_by_ref(&self) -> Value {
+		match self {
+			Self::Lua(value) => value.clone(),
+			_ => self.to_string().to_lua(),
+		}
+	}
+}
+
+impl From<Value> for FileError {
+	fn from(value: Value) -> Self {
+		Self::Lua(value)
+	}
+}
+
+impl From<FileError> for Value {
+	fn from(err: FileError) -> Self {
+		err.to_lua()
+	}
+}
+
+/// Calls a function of the `file` library.
+fn call_file<R: FromLuaMulti>(
+	name: &str,
+	args: impl IntoIterator<Item: ToLua>,
+) -> Result<R, FileError> {
+	let func = library_function("file", name)?;
+	Ok(func.call_as(args)?)
+}
+
+/// Checks that a path stays inside of the `DATA` folder.
+fn check_inside_data(path: &str) -> Result<(), FileError> {
+	let outside = Path::new(path)
+		.components()
+		.any(|component| !matches!(component, Component::Normal(_) | Component::CurDir));
+
+	match outside {
+		true => Err(FileError::OutsideData(path.to_string())),
+		false => Ok(()),
+	}
+}
+
+/// Checks that a file can be written to in the `DATA` folder.
+fn check_writable(path: &str) -> Result<(), FileError> {
+	check_inside_data(path)?;
+	match Path::new(path).extension().and_then(|ext| ext.to_str()) {
+		Some(ext) if WRITABLE_EXTENSIONS.contains(&&*ext.to_lowercase()) => Ok(()),
+		_ => Err(FileError::ForbiddenExtension(path.to_string())),
+	}
+}
+
+/// Reads the content of a file using [`file.Read`](https://wiki.facepunch.com/gmod/file.Read).
+pub fn read(path: &str, search: SearchPath) -> Result<Vec<u8>, FileError> {
+	let content: Option<LuaString> = call_file("Read", [path.to_lua(), search.to_lua()])?;
+	match content {
+		Some(content) => Ok(content.to_bytes().to_vec()),
+		None => Err(FileError::NotFound(path.to_string(), search)),
+	}
+}
+
+/// Reads the content of a file as a string.
+pub fn read_to_string(path: &str, search: SearchPath) -> Result<String, FileError> {
+	String::from_utf8(read(path, search)?).map_err(|_| FileError::InvalidUtf8(path.to_string()))
+}
+
+/// Writes to a file in the `DATA` folder using [`file.Write`](https://wiki.facepunch.com/gmod/file.Write),
+/// replacing its content.
+pub fn write(path: &str, content: impl AsRef<[u8]>) -> Result<(), FileError> {
+	check_writable(path)?;
+	let content = LuaString::from(content.as_ref());
+	call_file("Write", [path.to_lua(), content.to_lua()])
+}
+
+/// Appends to a file in the `DATA` folder using [`file.Append`](https://wiki.facepunch.com/gmod/file.Append).
+pub fn append(path: &str, content: impl AsRef<[u8]>) -> Result<(), FileError> {
+	check_writable(path)?;
+	let content = LuaString::from(content.as_ref());
+	call_file("Append", [path.to_lua(), content.to_lua()])
+}
+
+/// Creates a directory and its parents in the `DATA` folder using
+/// [`file.CreateDir`](https://wiki.facepunch.com/gmod/file.CreateDir).
+pub fn create_dir(path: &str) -> Result<(), FileError> {
+	check_inside_data(path)?;
+	call_file("CreateDir", [path])
+}
+
+/// Deletes a file or an empty directory in the `DATA` folder using
+/// [`file.Delete`](https://wiki.facepunch.com/gmod/file.Delete).
+pub fn delete(path: &str) -> Result<(), FileError> {
+	check_inside_data(path)?;
+	match exists(path, SearchPath::Data)? {
+		true => call_file("Delete", [path]),
+		false => Err(FileError::NotFound(path.to_string(), SearchPath::Data)),
+	}
+}
+
+/// Returns whether a file or directory exists using [`file.Exists`](https://wiki.facepunch.com/gmod/file.Exists).
+pub fn exists(path: &str, search: SearchPath) -> Result<bool, FileError> {
+	call_file("Exists", [path.to_lua(), search.to_lua()])
+}
+
+/// Returns whether a path is a directory using [`file.IsDir`](https://wiki.facepunch.com/gmod/file.IsDir).
+pub fn is_dir(path: &str, search: SearchPath) -> Result<bool, FileError> {
+	call_file("IsDir", [path.to_lua(), search.to_lua()])
+}
+
+/// Finds the files and directories matching a pattern using [`file.Find`](https://wiki.facepunch.com/gmod/file.Find).\
+/// The pattern can contain wildcards, such as `data/*.txt`.
+pub fn find(pattern: &str, search: SearchPath) -> Result<(Vec<String>, Vec<String>), FileError> {
+	let (files, dirs): (Option<Table>, Option<Table>) =
+		call_file("Find", [pattern.to_lua(), search.to_lua()])?;
+	let names = |tbl: Option<Table>| match tbl {
+		None => Vec::new(),
+		Some(tbl) => tbl
+			.ipairs()
+			.filter_map(|(_, name)| String::from_lua(name).ok())
+			.collect(),
+	};
+
+	Ok((names(files), names(dirs)))
+}
+
+/// The size of a file in bytes using [`file.Size`](https://wiki.facepunch.com/gmod/file.Size).
+pub fn size(path: &str, search: SearchPath) -> Result<u64, FileError> {
+	let size: Option<f64> = call_file("Size", [path.to_lua(), search.to_lua()])?;
+	match size {
+		Some(size) if size >= 0.0 => Ok(size as u64),
+		_ => Err(FileError::NotFound(path.to_string(), search)),
+	}
+}
+
+/// The last time a file was modified using [`file.Time`](https://wiki.facepunch.com/gmod/file.Time).
+pub fn time(path: &str, search: SearchPath) -> Result<SystemTime, FileError> {
+	let time: f64 = call_file("Time", [path.to_lua(), search.to_lua()])?;
+	match time > 0.0 {
+		true => Ok(SystemTime::UNIX_EPOCH + Duration::from_secs(time as u64)),
+		false => Err(FileError::NotFound(path.to_string(), search)),
+	}
+}
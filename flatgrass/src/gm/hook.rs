@@ -0,0 +1,401 @@
+use super::{Entity, Player, library_function};
+use crate::lua::util::{Return, Tuple};
+use crate::lua::{FnParams, FnReturn, FromLuaMulti};
+use crate::prelude::*;
+use std::cell::RefCell;
+
+#[cfg(feature = "async")]
+use futures_core::Stream;
+#[cfg(feature = "async")]
+use std::collections::VecDeque;
+#[cfg(feature = "async")]
+use std::pin::Pin;
+#[cfg(feature = "async")]
+use std::rc::Rc;
+#[cfg(feature = "async")]
+use std::task::{Context, Poll, Waker};
+
+thread_local! {
+	static HOOKS: RefCell<Vec<(String, Value)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// A [hook](https://wiki.facepunch.com/gmod/hook) event, with the arguments it receives and the values it returns.
+pub trait Event {
+	type Args: FnParams;
+	type Return: FnReturn;
+
+	fn name(&self) -> &str;
+}
+
+/// Events without a definition receive and return Lua values.
+impl Event for &str {
+	type Args = Tuple;
+	type Return = Tuple;
+
+	fn name(&self) -> &str {
+		self
+	}
+}
+
+/// Defines a typed hook event.
+macro_rules! event {
+	($(#[$meta:meta])* $ident:ident($($arg:ty),*) -> $ret:ty) => {
+		$(#[$meta])*
+		#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+		pub struct $ident;
+
+		impl Event for $ident {
+			type Args = ($($arg,)*);
+			type Return = $ret;
+
+			fn name(&self) -> &str {
+				::core::stringify!($ident)
+			}
+		}
+	};
+}
+
+event! {
+	/// See [GM:Think](https://wiki.facepunch.com/gmod/GM:Think).
+	Think() -> ()
+}
+
+event! {
+	/// See [GM:Tick](https://wiki.facepunch.com/gmod/GM:Tick).
+	Tick() -> ()
+}
+
+event! {
+	/// See [GM:Initialize](https://wiki.facepunch.com/gmod/GM:Initialize).
+	Initialize() -> ()
+}
+
+event! {
+	/// See [GM:InitPostEntity](https://wiki.facepunch.com/gmod/GM:InitPostEntity).
+	InitPostEntity() -> ()
+}
+
+event! {
+	/// See [GM:ShutDown](https://wiki.facepunch.com/gmod/GM:ShutDown).
+	ShutDown() -> ()
+}
+
+event! {
+	/// See [GM:PlayerConnect](https://wiki.facepunch.com/gmod/GM:PlayerConnect).
+	PlayerConnect(String, String) -> ()
+}
+
+event! {
+	/// See [GM:PlayerInitialSpawn](https://wiki.facepunch.com/gmod/GM:PlayerInitialSpawn).
+	PlayerInitialSpawn(Player, Option<bool>) -> ()
+}
+
+event! {
+	/// See [GM:PlayerSpawn](https://wiki.facepunch.com/gmod/GM:PlayerSpawn).
+	PlayerSpawn(Player, Option<bool>) -> ()
+}
+
+event! {
+	/// See [GM:PlayerDisconnected](https://wiki.facepunch.com/gmod/GM:PlayerDisconnected).
+	PlayerDisconnected(Player) -> ()
+}
+
+event! {
+	/// See [GM:PlayerDeath](https://wiki.facepunch.com/gmod/GM:PlayerDeath).
+	PlayerDeath(Player, Entity, Entity) -> ()
+}
+
+event! {
+	/// See [GM:PlayerSay](https://wiki.facepunch.com/gmod/GM:PlayerSay).\
+	/// Returning a string replaces the message.
+	PlayerSay(Player, String, bool) -> Option<String>
+}
+
+event! {
+	/// See [GM:OnEntityCreated](https://wiki.facepunch.com/gmod/GM:OnEntityCreated).
+	OnEntityCreated(Entity) -> ()
+}
+
+event! {
+	/// See [GM:EntityRemoved](https://wiki.facepunch.com/gmod/GM:EntityRemoved).
+	EntityRemoved(Entity, Option<bool>) -> ()
+}
+
+/// Adds a handler to an event using [`hook.Add`](https://wiki.facepunch.com/gmod/hook.Add).\
+/// Handlers are removed automatically when the module exits.
+pub fn add<E, F>(event: E, identifier: impl ToLua, func: F) -> Result<(), Value>
+where
+	E: Event,
+	F: Fn(E::Args) -> E::Return + 'static,
+{
+	let add = library_function("hook", "Add")?;
+	let identifier = identifier.to_lua();
+	let func = Function::from_fn(func);
+	call!(add: event.name(), &identifier, func)?;
+	HOOKS.with_borrow_mut(|hooks| {
+		hooks.retain(|(name, id)| name != event.name() || *id != identifier);
+		hooks.push((event.name().to_string(), identifier));
+	});
+
+	Ok(())
+}
+
+/// Removes a handler from an event using [`hook.Remove`](https://wiki.facepunch.com/gmod/hook.Remove).
+pub fn remove<E: Event>(event: E, identifier: impl ToLua) -> Result<(), Value> {
+	let remove = library_function("hook", "Remove")?;
+	let identifier = identifier.to_lua();
+	call!(remove: event.name(), &identifier)?;
+	HOOKS.with_borrow_mut(|hooks| {
+		hooks.retain(|(name, id)| name != event.name() || *id != identifier);
+	});
+
+	Ok(())
+}
+
+/// Runs the handlers of an event using [`hook.Run`](https://wiki.facepunch.com/gmod/hook.Run).
+pub fn run<E>(event: E, args: E::Args) -> Result<E::Return, Value>
+where
+	E: Event<Args: FnReturn, Return: FromLuaMulti>,
+{
+	let run = library_function("hook", "Run")?;
+	let args = event_args(args)?;
+	run.call_as([event.name().to_lua()].into_iter().chain(args))
+}
+
+/// Runs the handlers of an event, then the gamemode function, using [`hook.Call`](https://wiki.facepunch.com/gmod/hook.Call).
+pub fn call<E>(event: E, gamemode: Option<&Table>, args: E::Args) -> Result<E::Return, Value>
+where
+	E: Event<Args: FnReturn, Return: FromLuaMulti>,
+{
+	let call = library_function("hook", "Call")?;
+	let args = event_args(args)?;
+	let head = [event.name().to_lua(), gamemode.to_lua()];
+	call.call_as(head.into_iter().chain(args))
+}
+
+/// Converts the arguments of an event to Lua values.
+fn event_args<A: FnReturn>(args: A) -> Result<Tuple, Value> {
+	Lua::get(|lua| match args.fn_return(lua) {
+		Ok(Return::Values(values) | Return::Yield(values)) => {
+			Ok(values.into_iter().map(ToLua::to_lua).collect())
+		}
+		Err(err) => Err(err.to_lua()),
+	})
+}
+
+/// Waits for the next call of an event, returning its arguments.
+#[cfg(feature = "async")]
+pub async fn next<E>(event: E) -> Result<E::Args, Value>
+where
+	E: Event<Args: 'static, Return: Default>,
+{
+	Ok(stream(event)?.next().await)
+}
+
+/// Waits for the next call of an event with arguments matching the predicate, returning them.
+#[cfg(feature = "async")]
+pub async fn next_where<E, P>(event: E, predicate: P) -> Result<E::Args, Value>
+where
+	E: Event<Args: 'static, Return: Default>,
+	P: Fn(&E::Args) -> bool + 'static,
+{
+	Ok(stream_where(event, predicate)?.next().await)
+}
+
+/// Creates a stream of the arguments of every call of an event.
+///
+/// The arguments are buffered until the stream is polled, without limit by default,
+/// see [`EventStream::set_capacity`].
+#[cfg(feature = "async")]
+pub fn stream<E>(event: E) -> Result<EventStream<E>, Value>
+where
+	E: Event<Args: 'static, Return: Default>,
+{
+	stream_where(event, |_| true)
+}
+
+/// Creates a stream of the arguments of every call of an event matching the predicate.\
+/// Arguments not matching the predicate are not buffered.
+#[cfg(feature = "async")]
+pub fn stream_where<E, P>(event: E, predicate: P) -> Result<EventStream<E>, Value>
+where
+	E: Event<Args: 'static, Return: Default>,
+	P: Fn(&E::Args) -> bool + 'static,
+{
+	let listener = Rc::new(RefCell::new(Listener::new()));
+
+	let name = event.name().to_string();
+	let identifier = format!("__fg_stream_{:p}", Rc::as_ptr(&listener));
+	let handler = listener.clone();
+	add(event, identifier.as_str(), move |args: E::Args| {
+		if predicate(&args) {
+			handler.borrow_mut().push(args);
+		}
+
+		E::Return::default()
+	})?;
+
+	Ok(EventStream {
+		name,
+		identifier,
+		listener,
+	})
+}
+
+/// The values received by a stream, buffered until it is polled.
+#[cfg(feature = "async")]
+pub(super) struct Listener<T> {
+	queue: VecDeque<T>,
+	capacity: Option<usize>,
+	waker: Option<Waker>,
+}
+
+#[cfg(feature = "async")]
+impl<T> Listener<T> {
+	pub fn new() -> Self {
+		Self {
+			queue: VecDeque::new(),
+			capacity: None,
+			waker: None,
+		}
+	}
+
+	/// Buffers a value, dropping the oldest one if the buffer is full.
+	pub fn push(&mut self, value: T) {
+		if self
+			.capacity
+			.is_some_and(|capacity| self.queue.len() >= capacity)
+		{
+			self.queue.pop_front();
+		}
+
+		if self.capacity != Some(0) {
+			self.queue.push_back(value);
+			if let Some(waker) = self.waker.take() {
+				waker.wake();
+			}
+		}
+	}
+
+	pub fn poll_recv(&mut self, cx: &mut Context) -> Poll<T> {
+		match self.queue.pop_front() {
+			Some(value) => Poll::Ready(value),
+			None => {
+				self.waker = Some(cx.waker().clone());
+				Poll::Pending
+			}
+		}
+	}
+
+	/// Limits the number of buffered values, dropping the oldest ones.
+	pub fn set_capacity(&mut self, capacity: Option<usize>) {
+		self.capacity = capacity;
+		if let Some(capacity) = capacity {
+			let excess = self.queue.len().saturating_sub(capacity);
+			self.queue.drain(..excess);
+		}
+	}
+
+	pub fn len(&self) -> usize {
+		self.queue.len()
+	}
+}
+
+/// A stream of the arguments an event is called with, created with [`stream`] or [`stream_where`].\
+/// Its handler is removed when the stream is dropped.
+#[cfg(feature = "async")]
+#[must_use = "streams do nothing unless polled"]
+pub struct EventStream<E: Event> {
+	name: String,
+	identifier: String,
+	listener: Rc<RefCell<Listener<E::Args>>>,
+}
+
+#[cfg(feature = "async")]
+impl<E: Event> EventStream<E> {
+	/// Waits for the next call of the event, returning its arguments.
+	pub async fn next(&mut self) -> E::Args {
+		std::future::poll_fn(|cx| self.poll_recv(cx)).await
+	}
+
+	pub fn poll_recv(&mut self, cx: &mut Context) -> Poll<E::Args> {
+		self.listener.borrow_mut().poll_recv(cx)
+	}
+
+	/// Limits how many calls are buffered until the stream is polled, dropping the oldest ones.\
+	/// With a capacity of 1, only the arguments of the latest call are kept.
+	pub fn set_capacity(&mut self, capacity: usize) {
+		self.listener.borrow_mut().set_capacity(Some(capacity));
+	}
+
+	/// The number of calls buffered until the stream is polled.
+	pub fn len(&self) -> usize {
+		self.listener.borrow().len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+}
+
+#[cfg(feature = "async")]
+impl<E: Event> Stream for EventStream<E> {
+	type Item = E::Args;
+
+	fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<E::Args>> {
+		self.poll_recv(cx).map(Some)
+	}
+}
+
+#[cfg(feature = "async")]
+impl<E: Event> Drop for EventStream<E> {
+	fn drop(&mut self) {
+		if Lua::is_valid() {
+			let _ = remove(self.name.as_str(), self.identifier.as_str());
+		}
+	}
+}
+
+#[cfg(feature = "async")]
+impl<E: Event> std::fmt::Debug for EventStream<E> {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		f.debug_struct("EventStream")
+			.field("name", &self.name)
+			.field("identifier", &self.identifier)
+			.finish_non_exhaustive()
+	}
+}
+
+/// Removes every handler added from this module.
+pub(crate) fn clear() {
+	let hooks = HOOKS.take();
+	if let Ok(remove) = library_function("hook", "Remove") {
+		for (name, identifier) in hooks {
+			let _ = call!(remove: name, identifier);
+		}
+	}
+}
+
+#[cfg(all(test, feature = "async"))]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn listener_capacity() {
+		let mut cx = Context::from_waker(Waker::noop());
+		let mut listener = Listener::new();
+		for i in 0..5 {
+			listener.push(i);
+		}
+
+		assert_eq!(listener.len(), 5);
+		listener.set_capacity(Some(2));
+		assert_eq!(listener.poll_recv(&mut cx), Poll::Ready(3));
+
+		listener.push(5);
+		listener.push(6);
+		assert_eq!(listener.poll_recv(&mut cx), Poll::Ready(5));
+		assert_eq!(listener.poll_recv(&mut cx), Poll::Ready(6));
+		assert_eq!(listener.poll_recv(&mut cx), Poll::Pending);
+	}
+}
@@ -6,3 +6,21 @@ macro_rules! printfg {
 		$crate::gm::print(&::std::format!($($tt)*))
 	};
 }
+
+/// Prints a warning to the Garry's Mod console, prefixed with the current module.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __fg_warn {
+	($($tt:tt)*) => {
+		$crate::gm::log($crate::gm::Level::Warn, ::std::module_path!(), &::std::format!($($tt)*))
+	};
+}
+
+/// Prints an error to the Garry's Mod console, prefixed with the current module.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __fg_error {
+	($($tt:tt)*) => {
+		$crate::gm::log($crate::gm::Level::Error, ::std::module_path!(), &::std::format!($($tt)*))
+	};
+}
@@ -0,0 +1,627 @@
+use super::*;
+use crate::lua::Type;
+use crate::lua::error::FromLuaError;
+use std::ops::*;
+
+/// Constructs a GMod object with one of its global functions, such as `Vector`.
+fn try_construct<T: ToLua>(name: &str, args: impl IntoIterator<Item = T>) -> Result<Value, Value> {
+	let mut values = global_function(name)?.call(args)?;
+	Ok(values.pop_front().unwrap_or_default())
+}
+
+/// Same as [`try_construct`], but falls back to a plain table if the object cannot be constructed,
+/// for example in the menu state where the function isn't available.
+fn construct<T: ToLua>(
+	name: &str,
+	args: impl IntoIterator<Item = T>,
+	fallback: impl FnOnce() -> Table,
+) -> Value {
+	match try_construct(name, args) {
+		Ok(Value::Nil) | Err(_) => fallback().to_lua(),
+		Ok(value) => value,
+	}
+}
+
+/// Reads a number from a field of a GMod object, or of the table it falls back to.
+fn number_field(value: &Value, key: &str) -> Result<f32, FromLuaError<'static>> {
+	let field = match value {
+		Value::Userdata(udata) => udata.get(key),
+		Value::Table(tbl) => Ok(tbl.raw_get(key)),
+		_ => Ok(Value::Nil),
+	};
+
+	match field {
+		Ok(Value::Number(num)) => Ok(num as f32),
+		Ok(value) => Err(FromLuaError::bad_field(
+			key,
+			FromLuaError::expected_and_got_type(Type::Number, value.get_type()),
+		)),
+		Err(_) => Err(FromLuaError::bad_field(
+			key,
+			FromLuaError::expected_type(Type::Number),
+		)),
+	}
+}
+
+fn expected(name: &'static str, value: &Value) -> FromLuaError<'static> {
+	FromLuaError::expected_and_got(name, value.get_type().name())
+}
+
+/// Implements arithmetic operators for types made of three components.
+macro_rules! impl_ops {
+	($ty:ident, $a:ident, $b:ident, $c:ident) => {
+		impl Add for $ty {
+			type Output = Self;
+
+			fn add(self, rhs: Self) -> Self {
+				Self::new(self.$a + rhs.$a, self.$b + rhs.$b, self.$c + rhs.$c)
+			}
+		}
+
+		impl Sub for $ty {
+			type Output = Self;
+
+			fn sub(self, rhs: Self) -> Self {
+				Self::new(self.$a - rhs.$a, self.$b - rhs.$b, self.$c - rhs.$c)
+			}
+		}
+
+		impl Mul<f32> for $ty {
+			type Output = Self;
+
+			fn mul(self, rhs: f32) -> Self {
+				Self::new(self.$a * rhs, self.$b * rhs, self.$c * rhs)
+			}
+		}
+
+		impl Mul<$ty> for f32 {
+			type Output = $ty;
+
+			fn mul(self, rhs: $ty) -> $ty {
+				rhs * self
+			}
+		}
+
+		impl Div<f32> for $ty {
+			type Output = Self;
+
+			fn div(self, rhs: f32) -> Self {
+				Self::new(self.$a / rhs, self.$b / rhs, self.$c / rhs)
+			}
+		}
+
+		impl Neg for $ty {
+			type Output = Self;
+
+			fn neg(self) -> Self {
+				Self::new(-self.$a, -self.$b, -self.$c)
+			}
+		}
+
+		impl AddAssign for $ty {
+			fn add_assign(&mut self, rhs: Self) {
+				*self = *self + rhs;
+			}
+		}
+
+		impl SubAssign for $ty {
+			fn sub_assign(&mut self, rhs: Self) {
+				*self = *self - rhs;
+			}
+		}
+
+		impl MulAssign<f32> for $ty {
+			fn mul_assign(&mut self, rhs: f32) {
+				*self = *self * rhs;
+			}
+		}
+
+		impl DivAssign<f32> for $ty {
+			fn div_assign(&mut self, rhs: f32) {
+				*self = *self / rhs;
+			}
+		}
+
+		#[doc = concat!("Falls back to a plain table if the `", stringify!($ty), "` function isn't available.")]
+		impl ToLua for $ty {
+			fn to_lua_by_ref(&self) -> Value {
+				construct(stringify!($ty), [self.$a, self.$b, self.$c], || {
+					table! { $a: self.$a, $b: self.$b, $c: self.$c }
+				})
+			}
+		}
+
+		impl FromLua for $ty {
+			type Err = FromLuaError<'static>;
+
+			fn from_lua(value: Value) -> Result<Self, Self::Err> {
+				match value {
+					Value::Userdata(_) | Value::Table(_) => Ok(Self::new(
+						number_field(&value, stringify!($a))?,
+						number_field(&value, stringify!($b))?,
+						number_field(&value, stringify!($c))?,
+					)),
+					value => Err(expected(stringify!($ty), &value)),
+				}
+			}
+
+			fn no_value() -> Result<Self, Self::Err> {
+				Err(FromLuaError::expected(stringify!($ty)))
+			}
+		}
+	};
+}
+
+/// A position or direction in 3D space, converted from and to a GMod `Vector`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, PartialOrd)]
+pub struct Vector {
+	pub x: f32,
+	pub y: f32,
+	pub z: f32,
+}
+
+impl_ops!(Vector, x, y, z);
+
+impl Vector {
+	pub const ZERO: Self = Self::new(0.0, 0.0, 0.0);
+
+	pub const fn new(x: f32, y: f32, z: f32) -> Self {
+		Self { x, y, z }
+	}
+
+	pub fn dot(self, rhs: Self) -> f32 {
+		self.x * rhs.x + self.y * rhs.y + self.z * rhs.z
+	}
+
+	pub fn cross(self, rhs: Self) -> Self {
+		Self::new(
+			self.y * rhs.z - self.z * rhs.y,
+			self.z * rhs.x - self.x * rhs.z,
+			self.x * rhs.y - self.y * rhs.x,
+		)
+	}
+
+	pub fn length(self) -> f32 {
+		self.length_sqr().sqrt()
+	}
+
+	pub fn length_sqr(self) -> f32 {
+		self.dot(self)
+	}
+
+	pub fn distance(self, rhs: Self) -> f32 {
+		(self - rhs).length()
+	}
+
+	/// Returns a vector with the same direction and a length of 1, or a zero vector if its length is 0.
+	pub fn normalized(self) -> Self {
+		match self.length() {
+			0.0 => Self::ZERO,
+			len => self / len,
+		}
+	}
+
+	/// Returns the angle pointing in the direction of this vector, like `Vector:Angle()`.
+	pub fn angle(self) -> Angle {
+		if self.x == 0.0 && self.y == 0.0 {
+			let p = if self.z > 0.0 { 270.0 } else { 90.0 };
+			Angle::new(p, 0.0, 0.0)
+		} else {
+			let y = self.y.atan2(self.x).to_degrees().rem_euclid(360.0);
+			let p = (-self.z)
+				.atan2(self.x.hypot(self.y))
+				.to_degrees()
+				.rem_euclid(360.0);
+			Angle::new(p, y, 0.0)
+		}
+	}
+}
+
+/// Component-wise multiplication.
+impl Mul for Vector {
+	type Output = Self;
+
+	fn mul(self, rhs: Self) -> Self {
+		Self::new(self.x * rhs.x, self.y * rhs.y, self.z * rhs.z)
+	}
+}
+
+/// Euler angles in degrees, converted from and to a GMod `Angle`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, PartialOrd)]
+pub struct Angle {
+	pub p: f32,
+	pub y: f32,
+	pub r: f32,
+}
+
+impl_ops!(Angle, p, y, r);
+
+impl Angle {
+	pub const ZERO: Self = Self::new(0.0, 0.0, 0.0);
+
+	pub const fn new(p: f32, y: f32, r: f32) -> Self {
+		Self { p, y, r }
+	}
+
+	/// Returns the angle with every component between -180 and 180 degrees.
+	pub fn normalized(self) -> Self {
+		let normalize = |deg: f32| (deg + 180.0).rem_euclid(360.0) - 180.0;
+		Self::new(normalize(self.p), normalize(self.y), normalize(self.r))
+	}
+
+	/// Returns the forward, right and up directions of the angle.
+	pub fn directions(self) -> (Vector, Vector, Vector) {
+		let (sp, cp) = self.p.to_radians().sin_cos();
+		let (sy, cy) = self.y.to_radians().sin_cos();
+		let (sr, cr) = self.r.to_radians().sin_cos();
+
+		let forward = Vector::new(cp * cy, cp * sy, -sp);
+		let right = Vector::new(-sr * sp * cy + cr * sy, -sr * sp * sy - cr * cy, -sr * cp);
+		let up = Vector::new(cr * sp * cy + sr * sy, cr * sp * sy - sr * cy, cr * cp);
+		(forward, right, up)
+	}
+
+	pub fn forward(self) -> Vector {
+		self.directions().0
+	}
+
+	pub fn right(self) -> Vector {
+		self.directions().1
+	}
+
+	pub fn up(self) -> Vector {
+		self.directions().2
+	}
+}
+
+/// A color with an alpha channel, converted from and to a GMod `Color`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Color {
+	pub r: u8,
+	pub g: u8,
+	pub b: u8,
+	pub a: u8,
+}
+
+impl Color {
+	pub const WHITE: Self = Self::rgb(255, 255, 255);
+	pub const BLACK: Self = Self::rgb(0, 0, 0);
+
+	pub const fn new(r: u8, g: u8, b: u8, a: u8) -> Self {
+		Self { r, g, b, a }
+	}
+
+	pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+		Self::new(r, g, b, 255)
+	}
+
+	/// Returns the same color with a different alpha.
+	pub const fn with_alpha(self, a: u8) -> Self {
+		Self { a, ..self }
+	}
+
+	/// Linearly interpolates between two colors.
+	pub fn lerp(self, rhs: Self, t: f32) -> Self {
+		let lerp =
+			|a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t.clamp(0.0, 1.0)).round() as u8;
+		Self::new(
+			lerp(self.r, rhs.r),
+			lerp(self.g, rhs.g),
+			lerp(self.b, rhs.b),
+			lerp(self.a, rhs.a),
+		)
+	}
+}
+
+impl Default for Color {
+	fn default() -> Self {
+		Self::WHITE
+	}
+}
+
+/// Falls back to a plain table if the `Color` function isn't available.
+impl ToLua for Color {
+	fn to_lua_by_ref(&self) -> Value {
+		construct("Color", [self.r, self.g, self.b, self.a], || {
+			table! { r: self.r, g: self.g, b: self.b, a: self.a }
+		})
+	}
+}
+
+impl FromLua for Color {
+	type Err = FromLuaError<'static>;
+
+	fn from_lua(value: Value) -> Result<Self, Self::Err> {
+		let Value::Table(tbl) = value else {
+			return Err(expected("Color", &value));
+		};
+
+		let channel = |key: &str| match tbl.raw_get(key) {
+			Value::Number(num) => Ok(num.clamp(0.0, 255.0) as u8),
+			Value::Nil if key == "a" => Ok(255),
+			value => Err(FromLuaError::bad_field(
+				key,
+				FromLuaError::expected_and_got_type(Type::Number, value.get_type()),
+			)),
+		};
+
+		Ok(Self::new(
+			channel("r")?,
+			channel("g")?,
+			channel("b")?,
+			channel("a")?,
+		))
+	}
+
+	fn no_value() -> Result<Self, Self::Err> {
+		Err(FromLuaError::expected("Color"))
+	}
+}
+
+/// A 4x4 transformation matrix stored in rows, converted from and to a GMod `VMatrix`.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct VMatrix {
+	pub rows: [[f32; 4]; 4],
+}
+
+impl VMatrix {
+	pub const IDENTITY: Self = Self::new([
+		[1.0, 0.0, 0.0, 0.0],
+		[0.0, 1.0, 0.0, 0.0],
+		[0.0, 0.0, 1.0, 0.0],
+		[0.0, 0.0, 0.0, 1.0],
+	]);
+
+	pub const fn new(rows: [[f32; 4]; 4]) -> Self {
+		Self { rows }
+	}
+
+	pub fn from_translation(translation: Vector) -> Self {
+		let mut matrix = Self::IDENTITY;
+		matrix.set_translation(translation);
+		matrix
+	}
+
+	/// Creates a rotation matrix, like `VMatrix:SetAngles`.
+	pub fn from_angles(angles: Angle) -> Self {
+		let (forward, right, up) = angles.directions();
+		let left = -right;
+		Self::new([
+			[forward.x, left.x, up.x, 0.0],
+			[forward.y, left.y, up.y, 0.0],
+			[forward.z, left.z, up.z, 0.0],
+			[0.0, 0.0, 0.0, 1.0],
+		])
+	}
+
+	pub fn translation(&self) -> Vector {
+		Vector::new(self.rows[0][3], self.rows[1][3], self.rows[2][3])
+	}
+
+	pub fn set_translation(&mut self, translation: Vector) {
+		self.rows[0][3] = translation.x;
+		self.rows[1][3] = translation.y;
+		self.rows[2][3] = translation.z;
+	}
+
+	pub fn transpose(&self) -> Self {
+		let mut rows = [[0.0; 4]; 4];
+		for (i, row) in rows.iter_mut().enumerate() {
+			for (j, value) in row.iter_mut().enumerate() {
+				*value = self.rows[j][i];
+			}
+		}
+
+		Self::new(rows)
+	}
+
+	/// Transforms a point, applying the translation of the matrix.
+	pub fn transform_point(&self, point: Vector) -> Vector {
+		self.transform_direction(point) + self.translation()
+	}
+
+	/// Transforms a direction, ignoring the translation of the matrix.
+	pub fn transform_direction(&self, dir: Vector) -> Vector {
+		let row =
+			|i: usize| Vector::new(self.rows[i][0], self.rows[i][1], self.rows[i][2]).dot(dir);
+		Vector::new(row(0), row(1), row(2))
+	}
+}
+
+impl Default for VMatrix {
+	fn default() -> Self {
+		Self::IDENTITY
+	}
+}
+
+impl Mul for VMatrix {
+	type Output = Self;
+
+	fn mul(self, rhs: Self) -> Self {
+		let mut rows = [[0.0; 4]; 4];
+		for (i, row) in rows.iter_mut().enumerate() {
+			for (j, value) in row.iter_mut().enumerate() {
+				*value = (0..4).map(|k| self.rows[i][k] * rhs.rows[k][j]).sum();
+			}
+		}
+
+		Self::new(rows)
+	}
+}
+
+impl MulAssign for VMatrix {
+	fn mul_assign(&mut self, rhs: Self) {
+		*self = *self * rhs;
+	}
+}
+
+/// Transforms a point, like `VMatrix * Vector` in Lua.
+impl Mul<Vector> for VMatrix {
+	type Output = Vector;
+
+	fn mul(self, rhs: Vector) -> Vector {
+		self.transform_point(rhs)
+	}
+}
+
+/// Falls back to a plain table of rows if the `Matrix` function isn't available.
+impl ToLua for VMatrix {
+	fn to_lua_by_ref(&self) -> Value {
+		let rows = Table::new();
+		for row in self.rows {
+			rows.raw_push(Table::from_iter(row));
+		}
+
+		construct("Matrix", [&rows], || rows.clone())
+	}
+}
+
+impl FromLua for VMatrix {
+	type Err = FromLuaError<'static>;
+
+	fn from_lua(value: Value) -> Result<Self, Self::Err> {
+		let tbl: Table = match value {
+			Value::Table(tbl) => tbl,
+			Value::Userdata(udata) => udata
+				.call_method_as("ToTable", [] as [Value; 0])
+				.map_err(|_| FromLuaError::expected("VMatrix"))?,
+			value => return Err(expected("VMatrix", &value)),
+		};
+
+		let mut rows = [[0.0; 4]; 4];
+		for (i, row) in rows.iter_mut().enumerate() {
+			for (j, value) in row.iter_mut().enumerate() {
+				let num = match tbl.raw_get(i + 1) {
+					Value::Table(row) => row.raw_get(j + 1),
+					_ => Value::Nil,
+				};
+
+				*value = match num {
+					Value::Number(num) => num as f32,
+					_ => return Err(FromLuaError::expected("VMatrix")),
+				};
+			}
+		}
+
+		Ok(Self::new(rows))
+	}
+
+	fn no_value() -> Result<Self, Self::Err> {
+		Err(FromLuaError::expected("VMatrix"))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn assert_near(a: Vector, b: Vector) {
+		assert!((a - b).length() < 1e-4, "{a:?} != {b:?}");
+	}
+
+	#[test]
+	fn vector_arithmetic() {
+		let a = Vector::new(1.0, 2.0, 3.0);
+		let b = Vector::new(4.0, 5.0, 6.0);
+		assert_eq!(a + b, Vector::new(5.0, 7.0, 9.0));
+		assert_eq!(b - a, Vector::new(3.0, 3.0, 3.0));
+		assert_eq!(a * 2.0, 2.0 * a);
+		assert_eq!(a * b, Vector::new(4.0, 10.0, 18.0));
+		assert_eq!(-a / 2.0, Vector::new(-0.5, -1.0, -1.5));
+
+		let mut c = a;
+		c += b;
+		c -= a;
+		c *= 2.0;
+		c /= 4.0;
+		assert_eq!(c, b / 2.0);
+	}
+
+	#[test]
+	fn vector_products() {
+		let x = Vector::new(1.0, 0.0, 0.0);
+		let y = Vector::new(0.0, 1.0, 0.0);
+		assert_eq!(x.dot(y), 0.0);
+		assert_eq!(x.cross(y), Vector::new(0.0, 0.0, 1.0));
+		assert_eq!(Vector::new(3.0, 4.0, 0.0).length(), 5.0);
+		assert_eq!(Vector::new(3.0, 4.0, 0.0).length_sqr(), 25.0);
+		assert_eq!(x.distance(y), 2f32.sqrt());
+		assert_eq!(
+			Vector::new(0.0, 0.0, 2.0).normalized(),
+			Vector::new(0.0, 0.0, 1.0)
+		);
+		assert_eq!(Vector::ZERO.normalized(), Vector::ZERO);
+	}
+
+	#[test]
+	fn vector_angle() {
+		assert_eq!(Vector::new(1.0, 0.0, 0.0).angle(), Angle::ZERO);
+		assert_eq!(
+			Vector::new(0.0, 1.0, 0.0).angle(),
+			Angle::new(0.0, 90.0, 0.0)
+		);
+		assert_eq!(
+			Vector::new(0.0, 0.0, 1.0).angle(),
+			Angle::new(270.0, 0.0, 0.0)
+		);
+		assert_eq!(
+			Vector::new(0.0, 0.0, -1.0).angle(),
+			Angle::new(90.0, 0.0, 0.0)
+		);
+
+		let dir = Vector::new(1.0, -2.0, 0.5).normalized();
+		assert_near(dir.angle().forward(), dir);
+	}
+
+	#[test]
+	fn angle_normalized() {
+		let ang = Angle::new(190.0, -190.0, 540.0).normalized();
+		assert_eq!(ang, Angle::new(-170.0, 170.0, -180.0));
+		assert_eq!(
+			Angle::new(10.0, 20.0, 30.0) * 2.0,
+			Angle::new(20.0, 40.0, 60.0)
+		);
+	}
+
+	#[test]
+	fn angle_directions() {
+		let (forward, right, up) = Angle::ZERO.directions();
+		assert_near(forward, Vector::new(1.0, 0.0, 0.0));
+		assert_near(right, Vector::new(0.0, -1.0, 0.0));
+		assert_near(up, Vector::new(0.0, 0.0, 1.0));
+
+		let (forward, right, up) = Angle::new(0.0, 90.0, 0.0).directions();
+		assert_near(forward, Vector::new(0.0, 1.0, 0.0));
+		assert_near(right, Vector::new(1.0, 0.0, 0.0));
+		assert_near(up, Vector::new(0.0, 0.0, 1.0));
+
+		let (forward, _, up) = Angle::new(-90.0, 0.0, 0.0).directions();
+		assert_near(forward, Vector::new(0.0, 0.0, 1.0));
+		assert_near(up, Vector::new(-1.0, 0.0, 0.0));
+	}
+
+	#[test]
+	fn color_lerp() {
+		assert_eq!(
+			Color::BLACK.lerp(Color::WHITE, 0.5),
+			Color::rgb(128, 128, 128)
+		);
+		assert_eq!(Color::BLACK.lerp(Color::WHITE, 2.0), Color::WHITE);
+		assert_eq!(Color::WHITE.with_alpha(0).a, 0);
+	}
+
+	#[test]
+	fn matrix_transform() {
+		let translation = VMatrix::from_translation(Vector::new(1.0, 2.0, 3.0));
+		let rotation = VMatrix::from_angles(Angle::new(0.0, 90.0, 0.0));
+		let point = Vector::new(1.0, 0.0, 0.0);
+
+		assert_eq!(VMatrix::IDENTITY * point, point);
+		assert_eq!(translation * point, Vector::new(2.0, 2.0, 3.0));
+		assert_eq!(translation.transform_direction(point), point);
+		assert_near(rotation * point, Vector::new(0.0, 1.0, 0.0));
+		assert_near((translation * rotation) * point, Vector::new(1.0, 3.0, 3.0));
+		assert_eq!(rotation * VMatrix::IDENTITY, rotation);
+		assert_eq!(translation.transpose().transpose(), translation);
+	}
+}
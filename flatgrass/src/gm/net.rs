@@ -0,0 +1,579 @@
+use super::{Entity, Player, library_function};
+use crate::lua::FromLuaMulti;
+use crate::prelude::*;
+use std::cell::RefCell;
+use std::error::Error;
+use std::fmt::{self, Display};
+
+#[cfg(feature = "async")]
+use super::hook::Listener;
+#[cfg(feature = "async")]
+use futures_core::Stream;
+#[cfg(feature = "async")]
+use std::pin::Pin;
+#[cfg(feature = "async")]
+use std::rc::Rc;
+#[cfg(feature = "async")]
+use std::task::{Context, Poll};
+
+#[doc(inline)]
+#[cfg(feature = "macros")]
+pub use flatgrass_macros::NetMessage;
+
+thread_local! {
+	static RECEIVERS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+/// The number of bits used by [`net.WriteEntity`](https://wiki.facepunch.com/gmod/net.WriteEntity) to encode an entity index.
+pub const ENTITY_BITS: u32 = 16;
+
+/// A message sent through the [`net`](https://wiki.facepunch.com/gmod/net) library.
+///
+/// This can be derived for structs, in which case fields are encoded in order.\
+/// Integer fields use their full width unless annotated with `#[net(bits = N)]`,
+/// and the name of the message can be changed with `#[net(name = "...")]`.
+pub trait NetMessage: Sized {
+	const NAME: &'static str;
+
+	fn write(&self, writer: &mut Writer) -> Result<(), Value>;
+	fn read(reader: &mut Reader) -> Result<Self, NetError>;
+}
+
+/// A value that can be encoded in a net message.
+pub trait NetField: Sized {
+	fn write(&self, writer: &mut Writer) -> Result<(), Value>;
+	fn read(reader: &mut Reader) -> Result<Self, NetError>;
+}
+
+/// An integer that can be encoded using a specific number of bits.
+pub trait NetInt: NetField {
+	fn write_bits(&self, writer: &mut Writer, bits: u32) -> Result<(), Value>;
+	fn read_bits(reader: &mut Reader, bits: u32) -> Result<Self, NetError>;
+}
+
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub enum NetError {
+	/// Attempted to read more bits than what is left in the message.
+	Overflow { bits: u32, remaining: u32 },
+	/// An error raised by Lua.
+	Lua(Value),
+}
+
+impl Error for NetError {}
+impl Display for NetError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::Overflow { bits, remaining } => write!(
+				f,
+				"attempted to read {bits} bits from a net message with {remaining} bits left"
+			),
+			Self::Lua(Value::String(lstr)) => write!(f, "{lstr}"),
+			Self::Lua(value) => write!(f, "{value:?}"),
+		}
+	}
+}
+
+impl ToLua for NetError {
+	fn to_lua_by_ref(&self) -> Value {
+		match self {
+			Self::Lua(value) => value.clone(),
+			_ => self.to_string().to_lua(),
+		}
+	}
+}
+
+impl From<Value> for NetError {
+	fn from(value: Value) -> Self {
+		Self::Lua(value)
+	}
+}
+
+impl From<NetError> for Value {
+	fn from(err: NetError) -> Self {
+		err.to_lua()
+	}
+}
+
+/// Calls a function of the `net` library.
+fn call_net<R: FromLuaMulti>(
+	net: &Table,
+	name: &str,
+	args: impl IntoIterator<Item: ToLua>,
+) -> Result<R, Value> {
+	match net.raw_get(name) {
+		Value::Function(func) => func.call_as(args),
+		_ => Err(format!("'net.{name}' is not available").to_lua()),
+	}
+}
+
+/// Gets the `net` library.
+fn net_library() -> Result<Table, Value> {
+	match Table::globals().raw_get("net") {
+		Value::Table(net) => Ok(net),
+		_ => Err("'net' is not available".to_lua()),
+	}
+}
+
+/// Writes a net message started with [`start`].
+#[derive(Debug)]
+pub struct Writer {
+	net: Table,
+}
+
+impl Writer {
+	pub fn write_uint(&mut self, value: u32, bits: u32) -> Result<(), Value> {
+		call_net(&self.net, "WriteUInt", [value, bits])
+	}
+
+	pub fn write_int(&mut self, value: i32, bits: u32) -> Result<(), Value> {
+		call_net(&self.net, "WriteInt", [value.to_lua(), bits.to_lua()])
+	}
+
+	pub fn write_float(&mut self, value: f32) -> Result<(), Value> {
+		call_net(&self.net, "WriteFloat", [value])
+	}
+
+	pub fn write_double(&mut self, value: f64) -> Result<(), Value> {
+		call_net(&self.net, "WriteDouble", [value])
+	}
+
+	pub fn write_bool(&mut self, value: bool) -> Result<(), Value> {
+		call_net(&self.net, "WriteBool", [value])
+	}
+
+	pub fn write_string(&mut self, value: &str) -> Result<(), Value> {
+		call_net(&self.net, "WriteString", [value])
+	}
+
+	/// Writes an entity using [`net.WriteEntity`](https://wiki.facepunch.com/gmod/net.WriteEntity),
+	/// which writes its index on [`ENTITY_BITS`] bits, or 0 if it isn't valid.
+	pub fn write_entity(&mut self, entity: &Entity) -> Result<(), Value> {
+		call_net(&self.net, "WriteEntity", [entity])
+	}
+
+	/// Sends the message to a player using [`net.Send`](https://wiki.facepunch.com/gmod/net.Send).
+	pub fn send(self, player: &Player) -> Result<(), Value> {
+		call_net(&self.net, "Send", [player])
+	}
+
+	/// Sends the message to every player using [`net.Broadcast`](https://wiki.facepunch.com/gmod/net.Broadcast).
+	pub fn broadcast(self) -> Result<(), Value> {
+		call_net(&self.net, "Broadcast", [] as [Value; 0])
+	}
+
+	/// Sends the message to the server using [`net.SendToServer`](https://wiki.facepunch.com/gmod/net.SendToServer).
+	pub fn send_to_server(self) -> Result<(), Value> {
+		call_net(&self.net, "SendToServer", [] as [Value; 0])
+	}
+}
+
+/// Reads a received net message, refusing to read past its end.
+#[derive(Debug)]
+pub struct Reader {
+	net: Table,
+	len: u32,
+	read: u32,
+}
+
+impl Reader {
+	/// The number of bits left to read.
+	pub fn remaining(&self) -> u32 {
+		self.len - self.read
+	}
+
+	/// Consumes `bits` bits, or fails if there aren't enough left.
+	fn take(&mut self, bits: u32) -> Result<(), NetError> {
+		let remaining = self.remaining();
+		if bits > remaining {
+			Err(NetError::Overflow { bits, remaining })
+		} else {
+			self.read += bits;
+			Ok(())
+		}
+	}
+
+	pub fn read_uint(&mut self, bits: u32) -> Result<u32, NetError> {
+		self.take(bits)?;
+		let value: f64 = call_net(&self.net, "ReadUInt", [bits])?;
+		Ok(value as u32)
+	}
+
+	pub fn read_int(&mut self, bits: u32) -> Result<i32, NetError> {
+		self.take(bits)?;
+		let value: f64 = call_net(&self.net, "ReadInt", [bits])?;
+		Ok(value as i32)
+	}
+
+	pub fn read_float(&mut self) -> Result<f32, NetError> {
+		self.take(32)?;
+		Ok(call_net(&self.net, "ReadFloat", [] as [Value; 0])?)
+	}
+
+	pub fn read_double(&mut self) -> Result<f64, NetError> {
+		self.take(64)?;
+		Ok(call_net(&self.net, "ReadDouble", [] as [Value; 0])?)
+	}
+
+	pub fn read_bool(&mut self) -> Result<bool, NetError> {
+		self.take(1)?;
+		Ok(call_net(&self.net, "ReadBool", [] as [Value; 0])?)
+	}
+
+	/// Reads a string written with [`net.WriteString`](https://wiki.facepunch.com/gmod/net.WriteString).
+	///
+	/// The string is read one byte at a time up to its terminating null byte,
+	/// so that it can't be read past the end of the message.
+	pub fn read_string(&mut self) -> Result<String, NetError> {
+		let mut bytes = Vec::new();
+		loop {
+			match self.read_uint(8)? {
+				0 => break,
+				byte => bytes.push(byte as u8),
+			}
+		}
+
+		Ok(String::from_utf8_lossy(&bytes).into_owned())
+	}
+
+	/// Reads an entity using [`net.ReadEntity`](https://wiki.facepunch.com/gmod/net.ReadEntity).\
+	/// Invalid entities are written as index 0, so they are read back as the world entity.
+	pub fn read_entity(&mut self) -> Result<Entity, NetError> {
+		self.take(ENTITY_BITS)?;
+		Ok(call_net(&self.net, "ReadEntity", [] as [Value; 0])?)
+	}
+}
+
+/// Starts a net message using [`net.Start`](https://wiki.facepunch.com/gmod/net.Start).
+pub fn start(name: &str, unreliable: bool) -> Result<Writer, Value> {
+	let net = net_library()?;
+	call_net::<()>(&net, "Start", [name.to_lua(), unreliable.to_lua()])?;
+	Ok(Writer { net })
+}
+
+/// Registers the name of a message on the server using
+/// [`util.AddNetworkString`](https://wiki.facepunch.com/gmod/util.AddNetworkString).
+pub fn pool(name: &str) -> Result<(), Value> {
+	let add = library_function("util", "AddNetworkString")?;
+	call!(add: name).map(drop)
+}
+
+/// Starts a message and writes its content.
+pub fn write<M: NetMessage>(msg: &M) -> Result<Writer, Value> {
+	let mut writer = start(M::NAME, false)?;
+	msg.write(&mut writer)?;
+	Ok(writer)
+}
+
+/// Sends a message to a player.
+pub fn send<M: NetMessage>(msg: &M, player: &Player) -> Result<(), Value> {
+	write(msg)?.send(player)
+}
+
+/// Sends a message to every player.
+pub fn broadcast<M: NetMessage>(msg: &M) -> Result<(), Value> {
+	write(msg)?.broadcast()
+}
+
+/// Sends a message to the server.
+pub fn send_to_server<M: NetMessage>(msg: &M) -> Result<(), Value> {
+	write(msg)?.send_to_server()
+}
+
+/// Receives a message using [`net.Receive`](https://wiki.facepunch.com/gmod/net.Receive).\
+/// The callback receives the decoded message and the player who sent it, which is `None` on the client.\
+/// Receivers are removed automatically when the module exits.
+pub fn receive<M, F>(func: F) -> Result<(), Value>
+where
+	M: NetMessage,
+	F: Fn(M, Option<Player>) + 'static,
+{
+	receive_raw(M::NAME, move |mut reader, sender| {
+		let msg = M::read(&mut reader)?;
+		func(msg, sender);
+		Ok(())
+	})
+}
+
+/// Receives a message without decoding it.
+pub fn receive_raw<F>(name: &str, func: F) -> Result<(), Value>
+where
+	F: Fn(Reader, Option<Player>) -> Result<(), NetError> + 'static,
+{
+	register(name, func).map(drop)
+}
+
+/// Registers a receiver, returning the function passed to `net.Receive`.
+fn register<F>(name: &str, func: F) -> Result<Function, Value>
+where
+	F: Fn(Reader, Option<Player>) -> Result<(), NetError> + 'static,
+{
+	let net = net_library()?;
+	let func = Function::from_fn(
+		move |(len, sender): (f64, Option<Player>)| match net_library() {
+			Err(err) => Err(NetError::Lua(err)),
+			Ok(net) => {
+				let len = len as u32;
+				func(Reader { net, len, read: 0 }, sender)
+			}
+		},
+	);
+
+	call_net::<()>(&net, "Receive", [name.to_lua(), func.clone().to_lua()])?;
+	RECEIVERS.with_borrow_mut(|receivers| {
+		let name = name.to_lowercase();
+		receivers.retain(|receiver| *receiver != name);
+		receivers.push(name);
+	});
+
+	Ok(func)
+}
+
+/// Waits for the next message, returning it along with the player who sent it, which is `None` on the client.\
+/// This replaces the receiver of the message until it arrives.
+#[cfg(feature = "async")]
+pub async fn next<M: NetMessage + 'static>() -> Result<(M, Option<Player>), Value> {
+	Ok(stream::<M>()?.next().await)
+}
+
+/// Creates a stream of the messages received, along with the player who sent them, which is `None` on the client.\
+/// This replaces the receiver of the message until the stream is dropped.
+///
+/// The messages are buffered until the stream is polled, without limit by default,
+/// see [`NetStream::set_capacity`].
+#[cfg(feature = "async")]
+pub fn stream<M: NetMessage + 'static>() -> Result<NetStream<M>, Value> {
+	let listener = Rc::new(RefCell::new(Listener::new()));
+	let handler = listener.clone();
+	let func = register(M::NAME, move |mut reader, sender| {
+		let msg = M::read(&mut reader)?;
+		handler.borrow_mut().push((msg, sender));
+		Ok(())
+	})?;
+
+	Ok(NetStream { func, listener })
+}
+
+/// A message along with the player who sent it.
+#[cfg(feature = "async")]
+type Received<M> = (M, Option<Player>);
+
+/// A stream of the messages received, created with [`stream`].\
+/// Its receiver is removed when the stream is dropped, unless it was replaced.
+#[cfg(feature = "async")]
+#[must_use = "streams do nothing unless polled"]
+pub struct NetStream<M: NetMessage> {
+	func: Function,
+	listener: Rc<RefCell<Listener<Received<M>>>>,
+}
+
+#[cfg(feature = "async")]
+impl<M: NetMessage> NetStream<M> {
+	/// Waits for the next message, returning it along with the player who sent it.
+	pub async fn next(&mut self) -> (M, Option<Player>) {
+		std::future::poll_fn(|cx| self.poll_recv(cx)).await
+	}
+
+	pub fn poll_recv(&mut self, cx: &mut Context) -> Poll<(M, Option<Player>)> {
+		self.listener.borrow_mut().poll_recv(cx)
+	}
+
+	/// Limits how many messages are buffered until the stream is polled, dropping the oldest ones.\
+	/// With a capacity of 1, only the latest message is kept.
+	pub fn set_capacity(&mut self, capacity: usize) {
+		self.listener.borrow_mut().set_capacity(Some(capacity));
+	}
+
+	/// The number of messages buffered until the stream is polled.
+	pub fn len(&self) -> usize {
+		self.listener.borrow().len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+}
+
+#[cfg(feature = "async")]
+impl<M: NetMessage> Stream for NetStream<M> {
+	type Item = (M, Option<Player>);
+
+	fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+		self.poll_recv(cx).map(Some)
+	}
+}
+
+#[cfg(feature = "async")]
+impl<M: NetMessage> Drop for NetStream<M> {
+	fn drop(&mut self) {
+		if Lua::is_valid()
+			&& let Ok(net) = net_library()
+			&& let Value::Table(receivers) = net.raw_get("Receivers")
+		{
+			let name = M::NAME.to_lowercase();
+			if receivers.raw_get(name.as_str()) == Value::Function(self.func.clone()) {
+				receivers.raw_set(name.as_str(), Value::Nil);
+				RECEIVERS
+					.with_borrow_mut(|receivers| receivers.retain(|receiver| *receiver != name));
+			}
+		}
+	}
+}
+
+#[cfg(feature = "async")]
+impl<M: NetMessage> fmt::Debug for NetStream<M> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("NetStream")
+			.field("name", &M::NAME)
+			.finish_non_exhaustive()
+	}
+}
+
+/// Removes every receiver added from this module.
+pub(crate) fn clear() {
+	let receivers = RECEIVERS.take();
+	if let Ok(net) = net_library()
+		&& let Value::Table(table) = net.raw_get("Receivers")
+	{
+		for name in receivers {
+			table.raw_set(name, Value::Nil);
+		}
+	}
+}
+
+impl<T: NetMessage> NetField for T {
+	fn write(&self, writer: &mut Writer) -> Result<(), Value> {
+		NetMessage::write(self, writer)
+	}
+
+	fn read(reader: &mut Reader) -> Result<Self, NetError> {
+		NetMessage::read(reader)
+	}
+}
+
+/// Implements the NetField and NetInt traits for integer types.
+macro_rules! impl_netint {
+	($int:ty, $bits:literal, $write:ident, $read:ident, $as:ty) => {
+		impl NetField for $int {
+			fn write(&self, writer: &mut Writer) -> Result<(), Value> {
+				self.write_bits(writer, $bits)
+			}
+
+			fn read(reader: &mut Reader) -> Result<Self, NetError> {
+				Self::read_bits(reader, $bits)
+			}
+		}
+
+		impl NetInt for $int {
+			fn write_bits(&self, writer: &mut Writer, bits: u32) -> Result<(), Value> {
+				writer.$write(*self as $as, bits)
+			}
+
+			fn read_bits(reader: &mut Reader, bits: u32) -> Result<Self, NetError> {
+				reader.$read(bits).map(|value| value as Self)
+			}
+		}
+	};
+}
+
+impl_netint!(u8, 8, write_uint, read_uint, u32);
+impl_netint!(u16, 16, write_uint, read_uint, u32);
+impl_netint!(u32, 32, write_uint, read_uint, u32);
+impl_netint!(i8, 8, write_int, read_int, i32);
+impl_netint!(i16, 16, write_int, read_int, i32);
+impl_netint!(i32, 32, write_int, read_int, i32);
+
+impl NetField for bool {
+	fn write(&self, writer: &mut Writer) -> Result<(), Value> {
+		writer.write_bool(*self)
+	}
+
+	fn read(reader: &mut Reader) -> Result<Self, NetError> {
+		reader.read_bool()
+	}
+}
+
+impl NetField for f32 {
+	fn write(&self, writer: &mut Writer) -> Result<(), Value> {
+		writer.write_float(*self)
+	}
+
+	fn read(reader: &mut Reader) -> Result<Self, NetError> {
+		reader.read_float()
+	}
+}
+
+impl NetField for f64 {
+	fn write(&self, writer: &mut Writer) -> Result<(), Value> {
+		writer.write_double(*self)
+	}
+
+	fn read(reader: &mut Reader) -> Result<Self, NetError> {
+		reader.read_double()
+	}
+}
+
+impl NetField for String {
+	fn write(&self, writer: &mut Writer) -> Result<(), Value> {
+		writer.write_string(self)
+	}
+
+	fn read(reader: &mut Reader) -> Result<Self, NetError> {
+		reader.read_string()
+	}
+}
+
+impl NetField for Entity {
+	fn write(&self, writer: &mut Writer) -> Result<(), Value> {
+		writer.write_entity(self)
+	}
+
+	fn read(reader: &mut Reader) -> Result<Self, NetError> {
+		reader.read_entity()
+	}
+}
+
+/// Encoded as an entity, failing to read if it isn't a player.
+impl NetField for Player {
+	fn write(&self, writer: &mut Writer) -> Result<(), Value> {
+		writer.write_entity(self)
+	}
+
+	fn read(reader: &mut Reader) -> Result<Self, NetError> {
+		match reader.read_entity()?.to_player() {
+			Some(player) => Ok(player),
+			None => Err(NetError::Lua("expected a player".to_lua())),
+		}
+	}
+}
+
+/// Encoded as a boolean telling whether the value is present, followed by the value.
+impl<T: NetField> NetField for Option<T> {
+	fn write(&self, writer: &mut Writer) -> Result<(), Value> {
+		writer.write_bool(self.is_some())?;
+		match self {
+			Some(value) => value.write(writer),
+			None => Ok(()),
+		}
+	}
+
+	fn read(reader: &mut Reader) -> Result<Self, NetError> {
+		match reader.read_bool()? {
+			true => T::read(reader).map(Some),
+			false => Ok(None),
+		}
+	}
+}
+
+/// Encoded as its length on 16 bits, followed by its elements.
+impl<T: NetField> NetField for Vec<T> {
+	fn write(&self, writer: &mut Writer) -> Result<(), Value> {
+		let len = u16::try_from(self.len()).map_err(|_| "too many elements to write".to_lua())?;
+		len.write(writer)?;
+		self.iter().try_for_each(|value| value.write(writer))
+	}
+
+	fn read(reader: &mut Reader) -> Result<Self, NetError> {
+		let len = u16::read(reader)?;
+		(0..len).map(|_| T::read(reader)).collect()
+	}
+}
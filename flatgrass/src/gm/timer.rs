@@ -0,0 +1,227 @@
+use super::library_function;
+use crate::prelude::*;
+use std::cell::{Cell, RefCell};
+use std::time::Duration;
+
+thread_local! {
+	static TIMERS: RefCell<Vec<(String, u64)>> = const { RefCell::new(Vec::new()) };
+	static GENERATION: Cell<u64> = const { Cell::new(0) };
+	static SIMPLE_COUNT: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Tracks a timer created from this module, returning a number telling it apart from
+/// the timers previously created with the same identifier.
+fn track(id: &str) -> u64 {
+	let generation = GENERATION.replace(GENERATION.get() + 1);
+	TIMERS.with_borrow_mut(|timers| {
+		timers.retain(|(timer, _)| timer != id);
+		timers.push((id.to_string(), generation));
+	});
+
+	generation
+}
+
+fn untrack(id: &str) {
+	TIMERS.with_borrow_mut(|timers| {
+		timers.retain(|(timer, _)| timer != id);
+	});
+}
+
+/// Checks if a timer is still the one created with this generation.
+fn is_current(id: &str, generation: u64) -> bool {
+	TIMERS.with_borrow(|timers| {
+		timers
+			.iter()
+			.any(|timer| timer.0 == id && timer.1 == generation)
+	})
+}
+
+/// Runs a function once after a delay, like [`timer.Simple`](https://wiki.facepunch.com/gmod/timer.Simple).\
+/// Unlike `timer.Simple`, the timer is removed if the module exits before it runs.
+pub fn simple<F: FnOnce() + 'static>(delay: Duration, func: F) -> Result<(), Value> {
+	let id = SIMPLE_COUNT.with(|count| {
+		let n = count.get();
+		count.set(n + 1);
+		format!("__fg_simple_{count:p}_{n}")
+	});
+
+	let func = Cell::new(Some(func));
+	let untrack_id = id.clone();
+	create_raw(&id, delay, 1, move |()| {
+		untrack(&untrack_id);
+		if let Some(func) = func.take() {
+			func();
+		}
+	})
+	.map(drop)
+}
+
+/// Creates a timer using [`timer.Create`](https://wiki.facepunch.com/gmod/timer.Create),
+/// replacing any existing timer with the same identifier.\
+/// The function runs `reps` times, or forever if `reps` is 0.
+///
+/// The timer is removed when the returned handle is dropped, unless it is [detached](TimerHandle::detach).
+pub fn create<F: Fn() + 'static>(
+	id: &str,
+	delay: Duration,
+	reps: u32,
+	func: F,
+) -> Result<TimerHandle, Value> {
+	let generation = create_raw(id, delay, reps, move |()| func())?;
+	Ok(TimerHandle {
+		id: id.to_string(),
+		generation,
+		detached: false,
+	})
+}
+
+fn create_raw<F: Fn(()) + 'static>(
+	id: &str,
+	delay: Duration,
+	reps: u32,
+	func: F,
+) -> Result<u64, Value> {
+	let create = library_function("timer", "Create")?;
+	let func = Function::from_fn(func);
+	call!(create: id, delay.as_secs_f64(), reps, func)?;
+	Ok(track(id))
+}
+
+/// Changes the delay and optionally the repetitions of a timer using
+/// [`timer.Adjust`](https://wiki.facepunch.com/gmod/timer.Adjust).\
+/// Returns `false` if the timer doesn't exist.
+pub fn adjust(id: &str, delay: Duration, reps: Option<u32>) -> Result<bool, Value> {
+	let adjust = library_function("timer", "Adjust")?;
+	adjust.call_as([id.to_lua(), delay.as_secs_f64().to_lua(), reps.to_lua()])
+}
+
+/// Pauses a timer using [`timer.Pause`](https://wiki.facepunch.com/gmod/timer.Pause).\
+/// Returns `false` if the timer doesn't exist or is already paused.
+pub fn pause(id: &str) -> Result<bool, Value> {
+	let pause = library_function("timer", "Pause")?;
+	pause.call_as([id])
+}
+
+/// Resumes a paused timer using [`timer.UnPause`](https://wiki.facepunch.com/gmod/timer.UnPause).\
+/// Returns `false` if the timer doesn't exist or isn't paused.
+pub fn unpause(id: &str) -> Result<bool, Value> {
+	let unpause = library_function("timer", "UnPause")?;
+	unpause.call_as([id])
+}
+
+/// Pauses or resumes a timer using [`timer.Toggle`](https://wiki.facepunch.com/gmod/timer.Toggle).\
+/// Returns whether the timer is now running.
+pub fn toggle(id: &str) -> Result<bool, Value> {
+	let toggle = library_function("timer", "Toggle")?;
+	toggle.call_as([id])
+}
+
+/// The time left before a timer runs using [`timer.TimeLeft`](https://wiki.facepunch.com/gmod/timer.TimeLeft),
+/// or `None` if it doesn't exist.
+pub fn time_left(id: &str) -> Result<Option<Duration>, Value> {
+	let time_left = library_function("timer", "TimeLeft")?;
+	let left: Option<f64> = time_left.call_as([id])?;
+	Ok(left.map(|left| Duration::from_secs_f64(left.abs())))
+}
+
+/// The number of times a timer will still run using [`timer.RepsLeft`](https://wiki.facepunch.com/gmod/timer.RepsLeft),
+/// or `None` if it doesn't exist.
+pub fn reps_left(id: &str) -> Result<Option<u32>, Value> {
+	let reps_left = library_function("timer", "RepsLeft")?;
+	let left: Option<f64> = reps_left.call_as([id])?;
+	Ok(left.map(|left| left as u32))
+}
+
+/// Returns whether a timer exists using [`timer.Exists`](https://wiki.facepunch.com/gmod/timer.Exists).
+pub fn exists(id: &str) -> Result<bool, Value> {
+	let exists = library_function("timer", "Exists")?;
+	exists.call_as([id])
+}
+
+/// Removes a timer using [`timer.Remove`](https://wiki.facepunch.com/gmod/timer.Remove).
+pub fn remove(id: &str) -> Result<(), Value> {
+	let remove = library_function("timer", "Remove")?;
+	call!(remove: id)?;
+	untrack(id);
+	Ok(())
+}
+
+/// A timer created with [`create`], removed when dropped unless detached.
+///
+/// If another timer was created from this module with the same identifier since,
+/// it replaced this one and is left untouched when the handle is dropped or removed.
+#[derive(Debug)]
+#[must_use = "the timer is removed when the handle is dropped"]
+pub struct TimerHandle {
+	id: String,
+	generation: u64,
+	detached: bool,
+}
+
+impl TimerHandle {
+	pub fn id(&self) -> &str {
+		&self.id
+	}
+
+	pub fn adjust(&self, delay: Duration, reps: Option<u32>) -> Result<bool, Value> {
+		adjust(&self.id, delay, reps)
+	}
+
+	pub fn pause(&self) -> Result<bool, Value> {
+		pause(&self.id)
+	}
+
+	pub fn unpause(&self) -> Result<bool, Value> {
+		unpause(&self.id)
+	}
+
+	pub fn toggle(&self) -> Result<bool, Value> {
+		toggle(&self.id)
+	}
+
+	pub fn time_left(&self) -> Result<Option<Duration>, Value> {
+		time_left(&self.id)
+	}
+
+	pub fn reps_left(&self) -> Result<Option<u32>, Value> {
+		reps_left(&self.id)
+	}
+
+	pub fn exists(&self) -> Result<bool, Value> {
+		exists(&self.id)
+	}
+
+	/// Removes the timer now.
+	pub fn remove(mut self) -> Result<(), Value> {
+		self.detached = true;
+		match is_current(&self.id, self.generation) {
+			true => remove(&self.id),
+			false => Ok(()),
+		}
+	}
+
+	/// Lets the timer run after the handle is dropped, returning its identifier.\
+	/// It is still removed when the module exits.
+	pub fn detach(mut self) -> String {
+		self.detached = true;
+		std::mem::take(&mut self.id)
+	}
+}
+
+impl Drop for TimerHandle {
+	fn drop(&mut self) {
+		if !self.detached && Lua::is_valid() && is_current(&self.id, self.generation) {
+			let _ = remove(&self.id);
+		}
+	}
+}
+
+/// Removes every timer created from this module.
+pub(crate) fn clear() {
+	let timers = TIMERS.take();
+	if let Ok(remove) = library_function("timer", "Remove") {
+		for (id, _) in timers {
+			let _ = call!(remove: id);
+		}
+	}
+}
@@ -0,0 +1,162 @@
+use super::library_function;
+use crate::lua::LuaString;
+use crate::prelude::*;
+
+/// Gets a function of the `util` library if it is available.
+fn util_function(name: &str) -> Option<Function> {
+	library_function("util", name).ok()
+}
+
+/// Converts a table to JSON using [`util.TableToJSON`](https://wiki.facepunch.com/gmod/util.TableToJSON),
+/// or [`json_encode`] if it isn't available.
+pub fn to_json(tbl: &Table, pretty: bool) -> Result<String, Value> {
+	match util_function("TableToJSON") {
+		Some(to_json) => match to_json.call_as([tbl.to_lua(), pretty.to_lua()])? {
+			Some(json) => Ok(json),
+			None => Err("failed to convert table to JSON".to_lua()),
+		},
+		#[cfg(feature = "serde")]
+		None => json_encode(tbl, pretty).map_err(|err| err.to_string().to_lua()),
+		#[cfg(not(feature = "serde"))]
+		None => Err("'util.TableToJSON' is not available".to_lua()),
+	}
+}
+
+/// Converts JSON to a table using [`util.JSONToTable`](https://wiki.facepunch.com/gmod/util.JSONToTable),
+/// or [`json_decode`] if it isn't available.
+pub fn from_json(json: &str) -> Result<Table, Value> {
+	match util_function("JSONToTable") {
+		Some(from_json) => match from_json.call_as([json])? {
+			Some(tbl) => Ok(tbl),
+			None => Err("failed to convert JSON to table".to_lua()),
+		},
+		#[cfg(feature = "serde")]
+		None => json_decode(json).map_err(|err| err.to_string().to_lua()),
+		#[cfg(not(feature = "serde"))]
+		None => Err("'util.JSONToTable' is not available".to_lua()),
+	}
+}
+
+/// Compresses data using [`util.Compress`](https://wiki.facepunch.com/gmod/util.Compress).
+///
+/// Unlike [`decompress`], this doesn't fall back to [`lzma_compress`], which doesn't produce the same bytes.
+pub fn compress(data: &[u8]) -> Result<Vec<u8>, Value> {
+	let Some(compress) = util_function("Compress") else {
+		return Err("'util.Compress' is not available".to_lua());
+	};
+
+	let compressed: Option<LuaString> = compress.call_as([LuaString::from(data)])?;
+	match compressed {
+		Some(compressed) => Ok(compressed.to_bytes().to_vec()),
+		None => Err("failed to compress data".to_lua()),
+	}
+}
+
+/// Decompresses data using [`util.Decompress`](https://wiki.facepunch.com/gmod/util.Decompress),
+/// or [`lzma_decompress`] if it isn't available.
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>, Value> {
+	let decompressed = match util_function("Decompress") {
+		Some(decompress) => {
+			let decompressed: Option<LuaString> = decompress.call_as([LuaString::from(data)])?;
+			decompressed.map(|lstr| lstr.to_bytes().to_vec())
+		}
+		#[cfg(feature = "lzma")]
+		None => lzma_decompress(data),
+		#[cfg(not(feature = "lzma"))]
+		None => return Err("'util.Decompress' is not available".to_lua()),
+	};
+
+	decompressed.ok_or_else(|| "failed to decompress data".to_lua())
+}
+
+/// Converts a value to JSON without calling into Lua, formatted like `util.TableToJSON`.\
+/// This can be used outside of the main thread.
+#[cfg(feature = "serde")]
+pub fn json_encode<T: ?Sized + serde::Serialize>(
+	value: &T,
+	pretty: bool,
+) -> Result<String, serde_json::Error> {
+	match pretty {
+		false => serde_json::to_string(value),
+		true => {
+			let mut json = Vec::new();
+			let formatter = serde_json::ser::PrettyFormatter::with_indent(b"\t");
+			let mut serializer = serde_json::Serializer::with_formatter(&mut json, formatter);
+			value.serialize(&mut serializer)?;
+			Ok(String::from_utf8(json).expect("serde_json produced invalid UTF-8"))
+		}
+	}
+}
+
+/// Converts JSON to a value without calling into Lua.\
+/// This can be used outside of the main thread.
+#[cfg(feature = "serde")]
+pub fn json_decode<T: serde::de::DeserializeOwned>(json: &str) -> Result<T, serde_json::Error> {
+	serde_json::from_str(json)
+}
+
+/// Compresses data without calling into Lua.\
+/// This can be used outside of the main thread.
+///
+/// The output is in the same format as `util.Compress`, the LZMA properties followed by the size of the data on 8 bytes,
+/// so it can be decompressed with `util.Decompress` or [`lzma_decompress`].
+/// It is not byte-identical to what `util.Compress` produces for the same data, which is why [`compress`] doesn't use it.
+#[cfg(feature = "lzma")]
+pub fn lzma_compress(data: &[u8]) -> Vec<u8> {
+	if data.is_empty() {
+		return Vec::new();
+	}
+
+	let mut compressed = Vec::new();
+	let options = lzma_rs::compress::Options {
+		unpacked_size: lzma_rs::compress::UnpackedSize::WriteToHeader(Some(data.len() as u64)),
+	};
+
+	lzma_rs::lzma_compress_with_options(&mut &*data, &mut compressed, &options)
+		.expect("writing to a Vec cannot fail");
+	compressed
+}
+
+/// Decompresses data compressed with `util.Compress` or [`lzma_compress`] without calling into Lua,
+/// or returns `None` if the data is invalid.\
+/// This can be used outside of the main thread.
+#[cfg(feature = "lzma")]
+pub fn lzma_decompress(data: &[u8]) -> Option<Vec<u8>> {
+	if data.is_empty() {
+		return Some(Vec::new());
+	}
+
+	let mut decompressed = Vec::new();
+	lzma_rs::lzma_decompress(&mut &*data, &mut decompressed).ok()?;
+	Some(decompressed)
+}
+
+#[cfg(all(test, feature = "lzma"))]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn lzma_roundtrip() {
+		let text = "flatgrass ".repeat(1000);
+		let bytes = (0..=255).cycle().take(10_000).collect::<Vec<u8>>();
+		for data in [&b""[..], b"a", b"hello world", text.as_bytes(), &bytes] {
+			let compressed = lzma_compress(data);
+			assert_eq!(lzma_decompress(&compressed).as_deref(), Some(data));
+		}
+	}
+
+	#[test]
+	fn lzma_header() {
+		// the default properties (lc = 3, lp = 0, pb = 2), dictionary size, then the size of the data on 8 bytes
+		let compressed = lzma_compress(b"hello world");
+		assert_eq!(compressed[0], 0x5D);
+		assert_eq!(compressed[5..13], 11u64.to_le_bytes());
+	}
+
+	#[test]
+	fn lzma_invalid() {
+		assert_eq!(lzma_decompress(b"not lzma data"), None);
+		let compressed = lzma_compress(b"hello world");
+		assert_eq!(lzma_decompress(&compressed[..compressed.len() / 2]), None);
+	}
+}
@@ -7,7 +7,7 @@ pub use flatgrass_ffi as ffi;
 
 // Re-export the macros used to define functions.
 #[cfg(feature = "macros")]
-pub use flatgrass_macros::{entry, exit, function};
+pub use flatgrass_macros::{FromLua, entry, exit, function, module};
 
 /// Safe abstraction over the Lua C API.
 pub mod lua;
@@ -26,11 +26,11 @@ pub mod prelude {
 	#[doc(no_inline)]
 	pub use crate::lua::Lua;
 	#[doc(no_inline)]
-	pub use crate::lua::util::{Tuple, Upvalue, Yield};
+	pub use crate::lua::util::{This, Tuple, Upvalue, Yield};
 	#[doc(no_inline)]
 	pub use crate::lua::{Coroutine, FromLua, Function, Table, ToLua, Userdata, Value};
 	#[doc(no_inline)]
 	pub use crate::lua::{call, cfunction, resume, table};
 	#[doc(no_inline)]
-	pub use crate::{entry, exit, function};
+	pub use crate::{FromLua, entry, exit, function, module};
 }
@@ -14,6 +14,9 @@ macro_rules! stack_overflow {
 	};
 }
 
+#[cfg(feature = "async")]
+pub(crate) use stack_overflow;
+
 #[doc(inline)]
 pub use crate::{call, cfunction, resume, table};
 mod macros;
@@ -161,24 +164,26 @@ impl Lua {
 	}
 
 	#[doc(hidden)]
-	pub fn __fg_entry(&self) {
-		#[cfg(feature = "async")]
-		if let Value::Table(timer) = value::Table::globals().raw_get("timer") {
-			if let Value::Function(timer_create) = timer.raw_get("Create") {
-				static TICK: ffi::lua_CFunction = ffi::raw_function!(|state| unsafe {
-					Lua::enter(state, |lua| lua.runtime.tick());
-					0
-				});
-
-				let id = format!("__fg_poll_{:p}", self);
-				let _ = call!(timer_create: id, 0.0, 0.0, TICK);
-			}
+	pub fn __fg_entry(&self) -> Result<(), Value> {
+		#[cfg(feature = "tokio")]
+		if let Some(err) = self.runtime.tokio_error() {
+			return Err(format!("failed to start the Tokio runtime: {err}").to_lua());
 		}
+
+		#[cfg(feature = "async")]
+		let _ = self.runtime.set_driver(crate::task::Driver::default());
+		Ok(())
 	}
 
 	#[doc(hidden)]
 	pub fn __fg_exit(&self) {
 		#[cfg(feature = "async")]
 		self.runtime.shutdown();
+
+		crate::gm::clear();
+
+		crate::gm::flush_pending();
+
+		function::release_closures(self);
 	}
 }
@@ -3,7 +3,7 @@ use crate::lua::{Lua, ToLua, Type, Value};
 use std::borrow::Cow;
 use std::error::Error;
 use std::ffi::CStr;
-use std::fmt::{self, Debug, Display};
+use std::fmt::{self, Debug, Display, Write};
 
 #[derive(Default, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct LuaError<T> {
@@ -30,6 +30,20 @@ impl<T> LuaError<T> {
 	}
 }
 
+impl LuaError<String> {
+	/// Creates a new error from the message of `err` followed by the messages of its sources.
+	pub fn from_chain(err: &(dyn Error + 'static)) -> Self {
+		let mut msg = err.to_string();
+		let mut source = err.source();
+		while let Some(err) = source {
+			let _ = write!(msg, ": {err}");
+			source = err.source();
+		}
+
+		Self::new(msg)
+	}
+}
+
 impl<T: Error + 'static> Error for LuaError<T> {
 	fn source(&self) -> Option<&(dyn Error + 'static)> {
 		Some(&self.source)
@@ -51,39 +65,67 @@ impl<T: ToString> ToLua for LuaError<T> {
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct BadArgError<T> {
 	name: Option<String>,
+	param: Option<String>,
 	arg: i32,
 	source: T,
 }
 
 impl<T> BadArgError<T> {
-	pub fn new(mut arg: i32, source: T) -> Self {
+	pub fn new(arg: i32, source: T) -> Self {
+		let (name, method) = Self::called_function();
+		Self {
+			arg: if method { arg - 1 } else { arg },
+			param: None,
+			name,
+			source,
+		}
+	}
+
+	/// Creates an error for the receiver of a method, regardless of how the function was called.
+	pub fn new_self(source: T) -> Self {
+		let (name, _) = Self::called_function();
+		Self {
+			arg: 0,
+			param: None,
+			name,
+			source,
+		}
+	}
+
+	/// The name of the running function and whether it was called as a method.
+	fn called_function() -> (Option<String>, bool) {
 		Lua::get(|lua| unsafe {
 			let mut dbg = std::mem::zeroed();
-			let name = match ffi::lua_getstack(lua.to_ptr(), 0, &mut dbg) == 0 {
-				true => None,
+			match ffi::lua_getstack(lua.to_ptr(), 0, &mut dbg) == 0 {
+				true => (None, false),
 				false => {
 					ffi::lua_getinfo(lua.to_ptr(), c"n".as_ptr(), &mut dbg);
-					if ffi::libc::strcmp(dbg.namewhat, c"method".as_ptr()) == 0 {
-						arg -= 1;
-					}
-
+					let method = ffi::libc::strcmp(dbg.namewhat, c"method".as_ptr()) == 0;
 					if !dbg.name.is_null() {
 						let name = CStr::from_ptr(dbg.name);
-						Some(name.to_string_lossy().to_string())
+						(Some(name.to_string_lossy().to_string()), method)
 					} else {
-						None
+						(None, method)
 					}
 				}
-			};
-
-			Self { name, arg, source }
+			}
 		})
 	}
 
+	/// Sets the name of the parameter, used to describe it in the error message.
+	pub fn with_param(mut self, param: impl Into<String>) -> Self {
+		self.param = Some(param.into());
+		self
+	}
+
 	pub fn name(&self) -> Option<&str> {
 		self.name.as_deref()
 	}
 
+	pub fn param(&self) -> Option<&str> {
+		self.param.as_deref()
+	}
+
 	pub fn arg(&self) -> i32 {
 		self.arg
 	}
@@ -102,19 +144,64 @@ impl<T: Error + 'static> Error for BadArgError<T> {
 impl<T: ToString> Display for BadArgError<T> {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		let msg = self.source.to_string();
+		let arg = match self.param() {
+			Some(param) => format!("#{} '{param}'", self.arg),
+			None => format!("#{}", self.arg),
+		};
+
 		match (self.name(), self.arg) {
 			(Some(name), 0) => write!(f, "calling '{name}' on bad self ({msg})"),
-			(Some(name), arg) => write!(f, "bad argument #{arg} to '{name}' ({msg})"),
+			(Some(name), _) => write!(f, "bad argument {arg} to '{name}' ({msg})"),
 			(None, 0) => write!(f, "bad self ({msg})"),
-			(None, arg) => write!(f, "bad argument #{arg} ({msg})"),
+			(None, _) => write!(f, "bad argument {arg} ({msg})"),
 		}
 	}
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BadReturnError<T> {
+	ret: usize,
+	source: T,
+}
+
+impl<T> BadReturnError<T> {
+	pub const fn new(ret: usize, source: T) -> Self {
+		Self { ret, source }
+	}
+
+	pub const fn ret(&self) -> usize {
+		self.ret
+	}
+
+	pub const fn source(&self) -> &T {
+		&self.source
+	}
+}
+
+impl<T: Error + 'static> Error for BadReturnError<T> {
+	fn source(&self) -> Option<&(dyn Error + 'static)> {
+		Some(&self.source)
+	}
+}
+
+impl<T: ToString> Display for BadReturnError<T> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let msg = self.source.to_string();
+		write!(f, "bad return value #{} ({msg})", self.ret)
+	}
+}
+
+impl<T: ToString> ToLua for BadReturnError<T> {
+	fn to_lua_by_ref(&self) -> Value {
+		self.to_string().to_lua()
+	}
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum FromLuaError<'a> {
 	ExpectedAndGot(Cow<'a, str>, Cow<'a, str>),
 	Expected(Cow<'a, str>),
+	BadField(Cow<'a, str>, Cow<'a, str>),
 	NoValue,
 }
 
@@ -126,6 +213,10 @@ impl FromLuaError<'static> {
 	pub const fn expected_type(expected: Type) -> Self {
 		Self::Expected(Cow::Borrowed(expected.name()))
 	}
+
+	pub fn bad_field<T: ToString>(field: &str, err: T) -> Self {
+		Self::BadField(Cow::Owned(field.to_string()), Cow::Owned(err.to_string()))
+	}
 }
 
 impl<'a> FromLuaError<'a> {
@@ -155,6 +246,7 @@ impl Display for FromLuaError<'_> {
 		match self {
 			Self::ExpectedAndGot(expected, got) => write!(f, "{expected} expected, got {got}"),
 			Self::Expected(expected) => write!(f, "{expected} expected, got no value"),
+			Self::BadField(field, msg) => write!(f, "bad field '{field}' ({msg})"),
 			Self::NoValue => write!(f, "got no value"),
 		}
 	}
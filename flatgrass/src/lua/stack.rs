@@ -356,9 +356,15 @@ impl<'l> Stack<'l> {
 	/// Pushes a string on the stack.
 	#[track_caller]
 	pub fn push_string(&self, rstr: &str) {
+		self.push_bytes(rstr.as_bytes());
+	}
+
+	/// Pushes a string containing arbitrary bytes on the stack.
+	#[track_caller]
+	pub fn push_bytes(&self, bytes: &[u8]) {
 		if self.check_size(1) {
 			unsafe {
-				ffi::lua_pushlstring(self.to_ptr(), rstr.as_ptr().cast(), rstr.len());
+				ffi::lua_pushlstring(self.to_ptr(), bytes.as_ptr().cast(), bytes.len());
 			}
 		} else {
 			stack_overflow!();
@@ -7,5 +7,8 @@ pub use fn_return::*;
 mod from_lua;
 pub use from_lua::*;
 
+mod from_lua_multi;
+pub use from_lua_multi::*;
+
 mod to_lua;
 pub use to_lua::*;
@@ -1,13 +1,25 @@
 use crate::ffi::lua_upvalueindex;
 use crate::lua::error::{BadArgError, LuaError};
-use crate::lua::util::{Tuple, Upvalue};
-use crate::lua::{FromLua, Lua, ToLua};
+use crate::lua::util::{This, Tuple, Upvalue};
+use crate::lua::{FromLua, Lua, ToLua, Value};
+use std::convert::Infallible;
 use std::mem::replace;
 
 pub trait FnParam: Sized {
 	type Err: ToLua;
 
 	fn fn_param(lua: &Lua, arg: &mut i32, upv: &mut i32) -> Result<Self, Self::Err>;
+
+	/// Same as [`fn_param`](FnParam::fn_param), but `name` is used to describe the parameter in error messages.
+	fn fn_param_named(
+		lua: &Lua,
+		arg: &mut i32,
+		upv: &mut i32,
+		name: &str,
+	) -> Result<Self, Self::Err> {
+		let _ = name;
+		Self::fn_param(lua, arg, upv)
+	}
 }
 
 impl<T: FromLua<Err: ToString>> FnParam for T {
@@ -22,6 +34,38 @@ impl<T: FromLua<Err: ToString>> FnParam for T {
 
 		res.map_err(|err| LuaError::new(BadArgError::new(arg, err)))
 	}
+
+	fn fn_param_named(
+		lua: &Lua,
+		arg: &mut i32,
+		_: &mut i32,
+		name: &str,
+	) -> Result<Self, Self::Err> {
+		let arg = replace(arg, *arg + 1);
+		let res = match lua.stack().get_value(arg) {
+			Some(value) => T::from_lua(value),
+			None => T::no_value(),
+		};
+
+		res.map_err(|err| LuaError::new(BadArgError::new(arg, err).with_param(name)))
+	}
+}
+
+impl<T: FromLua<Err: ToString>> FnParam for This<T> {
+	type Err = LuaError<BadArgError<T::Err>>;
+
+	fn fn_param(lua: &Lua, arg: &mut i32, _: &mut i32) -> Result<Self, Self::Err> {
+		let arg = replace(arg, *arg + 1);
+		let res = match lua.stack().get_value(arg) {
+			Some(value) => T::from_lua(value),
+			None => T::no_value(),
+		};
+
+		match res {
+			Ok(value) => Ok(Self(value)),
+			Err(err) => Err(LuaError::new(BadArgError::new_self(err))),
+		}
+	}
 }
 
 impl<T: FromLua<Err: ToString>> FnParam for Upvalue<T> {
@@ -89,3 +133,48 @@ impl<T: FromLua<Err: ToString>> FnParam for Upvalue<Tuple<T>> {
 		Ok(Upvalue(tuple))
 	}
 }
+
+/// Parameters of a Rust closure called from Lua, usually a tuple of [`FnParam`]s.
+pub trait FnParams: Sized {
+	type Err: ToLua;
+
+	fn fn_params(lua: &Lua, arg: &mut i32, upv: &mut i32) -> Result<Self, Self::Err>;
+}
+
+impl<T: FnParam> FnParams for T {
+	type Err = T::Err;
+
+	fn fn_params(lua: &Lua, arg: &mut i32, upv: &mut i32) -> Result<Self, Self::Err> {
+		T::fn_param(lua, arg, upv)
+	}
+}
+
+impl FnParams for () {
+	type Err = Infallible;
+
+	fn fn_params(_: &Lua, _: &mut i32, _: &mut i32) -> Result<Self, Self::Err> {
+		Ok(())
+	}
+}
+
+/// Implements the FnParams trait for tuples.
+macro_rules! impl_fnparams_tuple {
+	($($ty:ident),+) => {
+		impl<$($ty: FnParam),+> FnParams for ($($ty,)+) {
+			type Err = Value;
+
+			fn fn_params(lua: &Lua, arg: &mut i32, upv: &mut i32) -> Result<Self, Self::Err> {
+				Ok(($($ty::fn_param(lua, arg, upv).map_err(ToLua::to_lua)?,)+))
+			}
+		}
+	};
+}
+
+impl_fnparams_tuple!(T1);
+impl_fnparams_tuple!(T1, T2);
+impl_fnparams_tuple!(T1, T2, T3);
+impl_fnparams_tuple!(T1, T2, T3, T4);
+impl_fnparams_tuple!(T1, T2, T3, T4, T5);
+impl_fnparams_tuple!(T1, T2, T3, T4, T5, T6);
+impl_fnparams_tuple!(T1, T2, T3, T4, T5, T6, T7);
+impl_fnparams_tuple!(T1, T2, T3, T4, T5, T6, T7, T8);
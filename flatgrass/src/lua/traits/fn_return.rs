@@ -1,6 +1,8 @@
+use crate::lua::error::LuaError;
 use crate::lua::util::{Return, Tuple, Yield};
 use crate::lua::{Lua, ToLua, Value};
 use std::convert::Infallible;
+use std::error::Error;
 
 pub trait FnReturn: Sized {
 	type Return: IntoIterator<Item: ToLua>;
@@ -27,50 +29,36 @@ impl FnReturn for () {
 	}
 }
 
-impl<T1: ToLua> FnReturn for (T1,) {
-	type Return = [Value; 1];
-	type Err = Infallible;
-
-	fn fn_return(self, _: &Lua) -> Result<Return<Self::Return>, Self::Err> {
-		Ok(Return::Values([self.0.to_lua()]))
-	}
-}
-
-impl<T1: ToLua, T2: ToLua> FnReturn for (T1, T2) {
-	type Return = [Value; 2];
-	type Err = Infallible;
-
-	fn fn_return(self, _: &Lua) -> Result<Return<Self::Return>, Self::Err> {
-		Ok(Return::Values([self.0.to_lua(), self.1.to_lua()]))
-	}
-}
-
-impl<T1: ToLua, T2: ToLua, T3: ToLua> FnReturn for (T1, T2, T3) {
-	type Return = [Value; 3];
-	type Err = Infallible;
+/// Implements the FnReturn trait for tuples.
+macro_rules! impl_fnreturn_tuple {
+	($n:literal: $($ty:ident $idx:tt),+) => {
+		impl<$($ty: ToLua),+> FnReturn for ($($ty,)+) {
+			type Return = [Value; $n];
+			type Err = Infallible;
 
-	fn fn_return(self, _: &Lua) -> Result<Return<Self::Return>, Self::Err> {
-		Ok(Return::Values([
-			self.0.to_lua(),
-			self.1.to_lua(),
-			self.2.to_lua(),
-		]))
-	}
+			fn fn_return(self, _: &Lua) -> Result<Return<Self::Return>, Self::Err> {
+				Ok(Return::Values([$(self.$idx.to_lua()),+]))
+			}
+		}
+	};
 }
 
-impl<T1: ToLua, T2: ToLua, T3: ToLua, T4: ToLua> FnReturn for (T1, T2, T3, T4) {
-	type Return = [Value; 4];
-	type Err = Infallible;
-
-	fn fn_return(self, _: &Lua) -> Result<Return<Self::Return>, Self::Err> {
-		Ok(Return::Values([
-			self.0.to_lua(),
-			self.1.to_lua(),
-			self.2.to_lua(),
-			self.3.to_lua(),
-		]))
-	}
-}
+impl_fnreturn_tuple!(1: T1 0);
+impl_fnreturn_tuple!(2: T1 0, T2 1);
+impl_fnreturn_tuple!(3: T1 0, T2 1, T3 2);
+impl_fnreturn_tuple!(4: T1 0, T2 1, T3 2, T4 3);
+impl_fnreturn_tuple!(5: T1 0, T2 1, T3 2, T4 3, T5 4);
+impl_fnreturn_tuple!(6: T1 0, T2 1, T3 2, T4 3, T5 4, T6 5);
+impl_fnreturn_tuple!(7: T1 0, T2 1, T3 2, T4 3, T5 4, T6 5, T7 6);
+impl_fnreturn_tuple!(8: T1 0, T2 1, T3 2, T4 3, T5 4, T6 5, T7 6, T8 7);
+impl_fnreturn_tuple!(9: T1 0, T2 1, T3 2, T4 3, T5 4, T6 5, T7 6, T8 7, T9 8);
+impl_fnreturn_tuple!(10: T1 0, T2 1, T3 2, T4 3, T5 4, T6 5, T7 6, T8 7, T9 8, T10 9);
+impl_fnreturn_tuple!(11: T1 0, T2 1, T3 2, T4 3, T5 4, T6 5, T7 6, T8 7, T9 8, T10 9, T11 10);
+impl_fnreturn_tuple!(12: T1 0, T2 1, T3 2, T4 3, T5 4, T6 5, T7 6, T8 7, T9 8, T10 9, T11 10, T12 11);
+impl_fnreturn_tuple!(13: T1 0, T2 1, T3 2, T4 3, T5 4, T6 5, T7 6, T8 7, T9 8, T10 9, T11 10, T12 11, T13 12);
+impl_fnreturn_tuple!(14: T1 0, T2 1, T3 2, T4 3, T5 4, T6 5, T7 6, T8 7, T9 8, T10 9, T11 10, T12 11, T13 12, T14 13);
+impl_fnreturn_tuple!(15: T1 0, T2 1, T3 2, T4 3, T5 4, T6 5, T7 6, T8 7, T9 8, T10 9, T11 10, T12 11, T13 12, T14 13, T15 14);
+impl_fnreturn_tuple!(16: T1 0, T2 1, T3 2, T4 3, T5 4, T6 5, T7 6, T8 7, T9 8, T10 9, T11 10, T12 11, T13 12, T14 13, T15 14, T16 15);
 
 impl<T: ToLua> FnReturn for Tuple<T> {
 	type Return = Self;
@@ -115,3 +103,27 @@ impl<T: FnReturn<Err = Infallible>, E: ToLua> FnReturn for Result<T, E> {
 		self?.fn_return(lua).map_err(|err| match err {})
 	}
 }
+
+/// Implements the FnReturn trait for results whose error is formatted along with its sources.
+macro_rules! impl_fnreturn_error {
+	($err:ty) => {
+		impl<T: FnReturn<Err = Infallible>> FnReturn for Result<T, $err> {
+			type Return = T::Return;
+			type Err = LuaError<String>;
+
+			fn fn_return(self, lua: &Lua) -> Result<Return<Self::Return>, LuaError<String>> {
+				match self {
+					Ok(value) => value.fn_return(lua).map_err(|err| match err {}),
+					Err(err) => Err(LuaError::from_chain(&*err)),
+				}
+			}
+		}
+	};
+}
+
+impl_fnreturn_error!(Box<dyn Error>);
+impl_fnreturn_error!(Box<dyn Error + Send>);
+impl_fnreturn_error!(Box<dyn Error + Send + Sync>);
+
+#[cfg(feature = "anyhow")]
+impl_fnreturn_error!(anyhow::Error);
@@ -0,0 +1,87 @@
+use crate::lua::error::BadReturnError;
+use crate::lua::util::Tuple;
+use crate::lua::{FromLua, ToLua, Value};
+use std::convert::Infallible;
+
+pub trait FromLuaMulti: Sized {
+	type Err: ToLua;
+
+	fn from_lua_multi(values: Tuple) -> Result<Self, Self::Err>;
+}
+
+/// Converts the next value, or the lack thereof, reporting its position on failure.
+fn from_lua_next<T: FromLua<Err: ToString>>(
+	values: &mut impl Iterator<Item = Value>,
+	ret: usize,
+) -> Result<T, BadReturnError<String>> {
+	let res = match values.next() {
+		Some(value) => T::from_lua(value),
+		None => T::no_value(),
+	};
+
+	res.map_err(|err| BadReturnError::new(ret, err.to_string()))
+}
+
+impl<T: FromLua<Err: ToString>> FromLuaMulti for T {
+	type Err = BadReturnError<T::Err>;
+
+	fn from_lua_multi(mut values: Tuple) -> Result<Self, Self::Err> {
+		let res = match values.pop_front() {
+			Some(value) => T::from_lua(value),
+			None => T::no_value(),
+		};
+
+		res.map_err(|err| BadReturnError::new(1, err))
+	}
+}
+
+impl FromLuaMulti for () {
+	type Err = Infallible;
+
+	fn from_lua_multi(_: Tuple) -> Result<Self, Self::Err> {
+		Ok(())
+	}
+}
+
+/// Implements the FromLuaMulti trait for tuples.
+macro_rules! impl_fromluamulti_tuple {
+	($($ty:ident $idx:tt),+) => {
+		impl<$($ty: FromLua<Err: ToString>),+> FromLuaMulti for ($($ty,)+) {
+			type Err = BadReturnError<String>;
+
+			fn from_lua_multi(values: Tuple) -> Result<Self, Self::Err> {
+				let mut values = values.into_iter();
+				Ok(($(from_lua_next::<$ty>(&mut values, $idx + 1)?,)+))
+			}
+		}
+	};
+}
+
+impl_fromluamulti_tuple!(T1 0);
+impl_fromluamulti_tuple!(T1 0, T2 1);
+impl_fromluamulti_tuple!(T1 0, T2 1, T3 2);
+impl_fromluamulti_tuple!(T1 0, T2 1, T3 2, T4 3);
+impl_fromluamulti_tuple!(T1 0, T2 1, T3 2, T4 3, T5 4);
+impl_fromluamulti_tuple!(T1 0, T2 1, T3 2, T4 3, T5 4, T6 5);
+impl_fromluamulti_tuple!(T1 0, T2 1, T3 2, T4 3, T5 4, T6 5, T7 6);
+impl_fromluamulti_tuple!(T1 0, T2 1, T3 2, T4 3, T5 4, T6 5, T7 6, T8 7);
+impl_fromluamulti_tuple!(T1 0, T2 1, T3 2, T4 3, T5 4, T6 5, T7 6, T8 7, T9 8);
+impl_fromluamulti_tuple!(T1 0, T2 1, T3 2, T4 3, T5 4, T6 5, T7 6, T8 7, T9 8, T10 9);
+impl_fromluamulti_tuple!(T1 0, T2 1, T3 2, T4 3, T5 4, T6 5, T7 6, T8 7, T9 8, T10 9, T11 10);
+impl_fromluamulti_tuple!(T1 0, T2 1, T3 2, T4 3, T5 4, T6 5, T7 6, T8 7, T9 8, T10 9, T11 10, T12 11);
+impl_fromluamulti_tuple!(T1 0, T2 1, T3 2, T4 3, T5 4, T6 5, T7 6, T8 7, T9 8, T10 9, T11 10, T12 11, T13 12);
+impl_fromluamulti_tuple!(T1 0, T2 1, T3 2, T4 3, T5 4, T6 5, T7 6, T8 7, T9 8, T10 9, T11 10, T12 11, T13 12, T14 13);
+impl_fromluamulti_tuple!(T1 0, T2 1, T3 2, T4 3, T5 4, T6 5, T7 6, T8 7, T9 8, T10 9, T11 10, T12 11, T13 12, T14 13, T15 14);
+impl_fromluamulti_tuple!(T1 0, T2 1, T3 2, T4 3, T5 4, T6 5, T7 6, T8 7, T9 8, T10 9, T11 10, T12 11, T13 12, T14 13, T15 14, T16 15);
+
+impl<T: FromLua<Err: ToString>> FromLuaMulti for Tuple<T> {
+	type Err = BadReturnError<T::Err>;
+
+	fn from_lua_multi(values: Tuple) -> Result<Self, Self::Err> {
+		values
+			.into_iter()
+			.enumerate()
+			.map(|(i, value)| T::from_lua(value).map_err(|err| BadReturnError::new(i + 1, err)))
+			.collect()
+	}
+}
@@ -11,6 +11,11 @@ pub struct Upvalue<T>(pub T);
 #[derive(Default, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Yield<T>(pub T);
 
+/// The receiver of a method, taken from the first argument.
+#[repr(transparent)]
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct This<T>(pub T);
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Return<T> {
 	Values(T),
@@ -1,7 +1,7 @@
 use crate::lua::error::FromLuaError;
 use crate::lua::util::Tuple;
 use crate::lua::value::Reference;
-use crate::lua::{FromLua, Lua, Stack, ToLua, Type, Value};
+use crate::lua::{FromLua, Function, Lua, Stack, ToLua, Type, Value};
 use crate::{ffi, resume};
 use std::cmp::Ordering;
 use std::fmt::{self, Debug};
@@ -75,6 +75,38 @@ impl Coroutine {
 		})
 	}
 
+	/// Creates a coroutine running a Lua function.
+	pub fn from_function(func: &Function) -> Self {
+		Lua::get(|lua| unsafe {
+			let stack = lua.stack();
+			if !stack.check_size(1) {
+				stack_overflow!();
+			}
+
+			let ptr = ffi::lua_newthread(lua.to_ptr());
+			Stack::new(ptr).push_function(func);
+			stack.pop_coroutine_unchecked()
+		})
+	}
+
+	/// The coroutine currently running, or `None` on the main thread.
+	pub fn running() -> Option<Self> {
+		Lua::get(|lua| unsafe {
+			let stack = lua.stack();
+			if !stack.check_size(1) {
+				stack_overflow!();
+			}
+
+			match ffi::lua_pushthread(lua.to_ptr()) {
+				0 => Some(stack.pop_coroutine_unchecked()),
+				_ => {
+					stack.pop_n(1);
+					None
+				}
+			}
+		})
+	}
+
 	pub fn to_ptr(&self) -> *mut ffi::lua_State {
 		Lua::get(|lua| unsafe {
 			let stack = lua.stack();
@@ -227,6 +259,10 @@ impl Iterator for Coroutine {
 	}
 }
 
+/// Resumes the coroutine on every poll, until it returns.\
+/// With the `async` feature, it is resumed once per tick of the runtime.
+///
+/// See [`Function::call_async`] to wait for the values yielded by the coroutine instead.
 impl Future for Coroutine {
 	type Output = Result<Tuple, Value>;
 
@@ -235,6 +271,9 @@ impl Future for Coroutine {
 			Err(err) => Poll::Ready(Err(err)),
 			Ok(Resume::Return(values)) => Poll::Ready(Ok(values)),
 			Ok(Resume::Yield(_)) => {
+				#[cfg(feature = "async")]
+				crate::task::time::wake_next_tick(cx.waker());
+				#[cfg(not(feature = "async"))]
 				cx.waker().wake_by_ref();
 				Poll::Pending
 			}
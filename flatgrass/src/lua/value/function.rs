@@ -1,8 +1,8 @@
 use crate::ffi;
 use crate::lua::error::FromLuaError;
-use crate::lua::util::Tuple;
+use crate::lua::util::{Return, Tuple};
 use crate::lua::value::Reference;
-use crate::lua::{FromLua, Lua, Stack, ToLua, Type, Value};
+use crate::lua::{FnParams, FnReturn, FromLua, FromLuaMulti, Lua, Stack, ToLua, Type, Value};
 use std::cmp::Ordering;
 use std::fmt::{self, Debug};
 use std::hash::{Hash, Hasher};
@@ -67,6 +67,96 @@ impl Function {
 		})
 	}
 
+	/// Creates a Lua function that calls a Rust closure.\
+	/// The closure is dropped when the function is garbage collected, or when the module exits.
+	pub fn from_fn<A, R, F>(func: F) -> Self
+	where
+		A: FnParams,
+		R: FnReturn,
+		F: Fn(A) -> R + 'static,
+	{
+		unsafe extern "C-unwind" fn call<A, R, F>(state: *mut ffi::lua_State) -> ffi::libc::c_int
+		where
+			A: FnParams,
+			R: FnReturn,
+			F: Fn(A) -> R + 'static,
+		{
+			let res = unsafe {
+				Lua::enter(state, |lua| {
+					let udata = ffi::lua_touserdata(state, ffi::lua_upvalueindex(1));
+					let res = match (*udata.cast::<Closure>()).ptr.cast::<F>().as_ref() {
+						None => Err("attempt to call a released function".to_lua()),
+						Some(func) => {
+							let (mut arg, mut upv) = (1, 2);
+							match A::fn_params(lua, &mut arg, &mut upv) {
+								Ok(args) => func(args).fn_return(lua).map_err(ToLua::to_lua),
+								Err(err) => Err(err.to_lua()),
+							}
+						}
+					};
+
+					match res {
+						Ok(Return::Values(values)) => {
+							Some(Return::Values(lua.stack().push_many(values)))
+						}
+						Ok(Return::Yield(values)) => {
+							Some(Return::Yield(lua.stack().push_many(values)))
+						}
+						Err(err) => {
+							lua.stack().clear();
+							lua.stack().push_any(err);
+							None
+						}
+					}
+				})
+			};
+
+			match res {
+				None => unsafe { ffi::lua_error(state) },
+				Some(Return::Values(n)) => n,
+				Some(Return::Yield(n)) => unsafe { ffi::lua_yield(state, n) },
+			}
+		}
+
+		unsafe fn drop<F>(ptr: *mut ()) {
+			unsafe { std::mem::drop(Box::from_raw(ptr.cast::<F>())) }
+		}
+
+		static GC: ffi::lua_CFunction = ffi::raw_function!(|state| unsafe {
+			let closure = ffi::lua_touserdata(state, 1).cast::<Closure>();
+			Lua::enter(state, |_| Closure::release(closure));
+			0
+		});
+
+		Lua::get(|lua| unsafe {
+			let stack = lua.stack();
+			if !stack.check_size(5) {
+				stack_overflow!();
+			}
+
+			let state = lua.to_ptr();
+			let udata = ffi::lua_newuserdata(state, size_of::<Closure>());
+			udata.cast::<Closure>().write(Closure {
+				ptr: Box::into_raw(Box::new(func)).cast(),
+				drop: drop::<F>,
+			});
+
+			ffi::lua_newtable(state);
+			ffi::lua_pushcfunction(state, GC);
+			ffi::lua_setfield(state, -2, c"__gc".as_ptr());
+			ffi::lua_setmetatable(state, -2);
+
+			Closure::push_tracked(state);
+			ffi::lua_pushvalue(state, -2);
+			ffi::lua_pushboolean(state, 1);
+			ffi::lua_rawset(state, -3);
+			ffi::lua_pop(state, 1);
+
+			ffi::lua_pushcclosure(state, call::<A, R, F>, 1);
+			stack.pop_function_unchecked()
+		})
+	}
+
 	pub fn to_ptr(&self) -> *const ffi::libc::c_void {
 		Lua::get(|lua| unsafe {
 			let stack = lua.stack();
@@ -107,6 +197,102 @@ impl Function {
 			}
 		})
 	}
+
+	/// Calls the function and converts the values it returned.\
+	/// Conversion errors are turned into Lua values and report the position of the faulty value.
+	pub fn call_as<R: FromLuaMulti, T: IntoIterator<Item: ToLua>>(
+		&self,
+		args: T,
+	) -> Result<R, Value> {
+		R::from_lua_multi(self.call(args)?).map_err(ToLua::to_lua)
+	}
+
+	/// Calls the function in a new coroutine, resuming it once what it yields is ready:
+	/// - nothing resumes it on the next tick.
+	/// - a number resumes it after that many seconds.
+	/// - a function is called with a function resuming the coroutine with its arguments.
+	/// - a promise resumes it with its values, or `nil` followed by its error.
+	#[cfg(feature = "async")]
+	pub fn call_async<T: IntoIterator<Item: ToLua>>(
+		&self,
+		args: T,
+	) -> impl Future<Output = Result<Tuple, Value>> + 'static {
+		let cor = crate::lua::Coroutine::from_function(self);
+		let args = args.into_iter().map(ToLua::to_lua).collect();
+		crate::task::run_coroutine(cor, args)
+	}
+
+	/// Calls the function in a new coroutine, converting its return values, see [`call_async`](Self::call_async).
+	#[cfg(feature = "async")]
+	pub async fn call_async_as<R: FromLuaMulti, T: IntoIterator<Item: ToLua>>(
+		&self,
+		args: T,
+	) -> Result<R, Value> {
+		let values = self.call_async(args).await?;
+		R::from_lua_multi(values).map_err(ToLua::to_lua)
+	}
+}
+
+/// The contents of the userdata owning a Rust closure.
+#[repr(C)]
+struct Closure {
+	ptr: *mut (),
+	drop: unsafe fn(*mut ()),
+}
+
+impl Closure {
+	/// Drops the closure, if it hasn't been dropped already.
+	unsafe fn release(closure: *mut Self) {
+		unsafe {
+			let ptr = std::mem::replace(&mut (*closure).ptr, std::ptr::null_mut());
+			if !ptr.is_null() {
+				((*closure).drop)(ptr);
+			}
+		}
+	}
+
+	/// Pushes the weak table containing the closures created by this module.
+	unsafe fn push_tracked(state: *mut ffi::lua_State) {
+		static KEY: u8 = 0;
+
+		unsafe {
+			let key = (&raw const KEY).cast_mut().cast();
+			ffi::lua_pushlightuserdata(state, key);
+			ffi::lua_rawget(state, ffi::LUA_REGISTRYINDEX);
+			if ffi::lua_type(state, -1) != ffi::LUA_TTABLE {
+				ffi::lua_pop(state, 1);
+				ffi::lua_newtable(state);
+				ffi::lua_newtable(state);
+				ffi::lua_pushstring(state, c"k".as_ptr());
+				ffi::lua_setfield(state, -2, c"__mode".as_ptr());
+				ffi::lua_setmetatable(state, -2);
+				ffi::lua_pushlightuserdata(state, key);
+				ffi::lua_pushvalue(state, -2);
+				ffi::lua_rawset(state, ffi::LUA_REGISTRYINDEX);
+			}
+		}
+	}
+}
+
+/// Drops every closure created by this module, as its code is about to be unloaded.
+pub(crate) fn release_closures(lua: &Lua) {
+	unsafe {
+		let state = lua.to_ptr();
+		if !lua.stack().check_size(5) {
+			stack_overflow!();
+		}
+
+		Closure::push_tracked(state);
+		ffi::lua_pushnil(state);
+		while ffi::lua_next(state, -2) != 0 {
+			ffi::lua_pop(state, 1);
+			Closure::release(ffi::lua_touserdata(state, -1).cast());
+			ffi::lua_pushnil(state);
+			ffi::lua_setmetatable(state, -2);
+		}
+
+		ffi::lua_pop(state, 1);
+	}
 }
 
 impl ToLua for Function {
@@ -172,6 +172,22 @@ impl From<String> for LuaString {
 	}
 }
 
+impl From<&[u8]> for LuaString {
+	fn from(value: &[u8]) -> Self {
+		Lua::get(|lua| unsafe {
+			let stack = lua.stack();
+			stack.push_bytes(value);
+			stack.pop_lua_string_unchecked()
+		})
+	}
+}
+
+impl From<Vec<u8>> for LuaString {
+	fn from(value: Vec<u8>) -> Self {
+		Self::from(value.as_slice())
+	}
+}
+
 impl From<&CStr> for LuaString {
 	fn from(value: &CStr) -> Self {
 		Lua::get(|lua| unsafe {
@@ -1,7 +1,8 @@
 use crate::ffi;
 use crate::lua::error::FromLuaError;
+use crate::lua::util::Tuple;
 use crate::lua::value::Reference;
-use crate::lua::{FromLua, Lua, Stack, ToLua, Type, Value};
+use crate::lua::{FromLua, FromLuaMulti, Lua, Stack, ToLua, Type, Value};
 use std::cmp::Ordering;
 use std::fmt::{self, Debug};
 use std::rc::Rc;
@@ -67,6 +68,76 @@ impl Userdata {
 			ptr.cast()
 		})
 	}
+
+	/// Indexes the userdata, invoking its metamethods.
+	pub fn get<K: ToLua>(&self, key: K) -> Result<Value, Value> {
+		static GET: ffi::lua_CFunction = ffi::raw_function!(|state| unsafe {
+			ffi::lua_gettable(state, 1);
+			1
+		});
+
+		Lua::get(|lua| {
+			let stack = lua.stack();
+			stack.push_c_function(GET);
+			stack.push_userdata(self);
+			stack.push_any(key);
+			unsafe {
+				match ffi::lua_pcall(lua.to_ptr(), 2, 1, 0) {
+					0 => Ok(stack.pop_value_unchecked()),
+					_ => Err(stack.pop_value_unchecked()),
+				}
+			}
+		})
+	}
+
+	/// Assigns a value to a key of the userdata, invoking its metamethods.
+	pub fn set<K: ToLua, V: ToLua>(&self, key: K, value: V) -> Result<(), Value> {
+		static SET: ffi::lua_CFunction = ffi::raw_function!(|state| unsafe {
+			ffi::lua_settable(state, 1);
+			0
+		});
+
+		Lua::get(|lua| {
+			let stack = lua.stack();
+			stack.push_c_function(SET);
+			stack.push_userdata(self);
+			stack.push_any(key);
+			stack.push_any(value);
+			unsafe {
+				match ffi::lua_pcall(lua.to_ptr(), 3, 0, 0) {
+					0 => Ok(()),
+					_ => Err(stack.pop_value_unchecked()),
+				}
+			}
+		})
+	}
+
+	/// Calls a method of the userdata, like `udata:name(...)` would in Lua.
+	pub fn call_method<T: IntoIterator<Item: ToLua>>(
+		&self,
+		name: &str,
+		args: T,
+	) -> Result<Tuple, Value> {
+		match self.get(name)? {
+			Value::Function(func) => {
+				let args = args.into_iter().map(ToLua::to_lua);
+				func.call([self.to_lua_by_ref()].into_iter().chain(args))
+			}
+			value => {
+				let ty = value.get_type().name();
+				Err(format!("attempt to call method '{name}' (a {ty} value)").to_lua())
+			}
+		}
+	}
+
+	/// Calls a method of the userdata and converts the values it returned.
+	pub fn call_method_as<R, T>(&self, name: &str, args: T) -> Result<R, Value>
+	where
+		R: FromLuaMulti,
+		T: IntoIterator<Item: ToLua>,
+	{
+		R::from_lua_multi(self.call_method(name, args)?).map_err(ToLua::to_lua)
+	}
 }
 
 impl ToLua for Userdata {
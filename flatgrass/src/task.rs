@@ -1,19 +1,79 @@
-use crate::lua::{LightUserdata, Lua};
+use crate::ffi;
+use crate::gm::library_function;
+use crate::lua::util::{Return, Tuple};
+use crate::lua::{Coroutine, FnReturn, Function, Lua, Table, ToLua, Value, call};
 use avenir::{Executor, blocking};
-use futures_channel::oneshot::Sender;
-use std::cell::{Cell, RefCell};
-use std::collections::HashMap;
+use budget::{Budgeted, Meter};
+use metrics::{BlockingGuard, Stats};
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
 
 #[doc(inline)]
 pub use avenir::Task;
 
+pub mod library;
 pub mod time;
 
-pub fn spawn<F: IntoFuture + 'static>(future: F) -> Task<F::Output> {
+mod budget;
+pub use budget::Budget;
+
+mod cancel;
+pub use cancel::{CancellationToken, Cancelled};
+
+mod handle;
+pub use handle::MainThreadHandle;
+
+mod join_set;
+pub use join_set::JoinSet;
+
+mod metrics;
+pub use metrics::{Metrics, TaskInfo, add_command};
+
+mod promise;
+pub use promise::Promise;
+
+mod resume;
+pub(crate) use resume::{Suspension, game_time_tag, is_task, run_coroutine};
+
+/// The error returned when spawning a task on a runtime that was shut down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ShutdownError;
+
+impl std::fmt::Display for ShutdownError {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		f.write_str("cannot spawn tasks on a runtime that was shut down")
+	}
+}
+
+impl std::error::Error for ShutdownError {}
+
+impl ToLua for ShutdownError {
+	fn to_lua_by_ref(&self) -> Value {
+		self.to_string().to_lua()
+	}
+}
+
+impl From<ShutdownError> for Value {
+	fn from(err: ShutdownError) -> Self {
+		err.to_lua()
+	}
+}
+
+pub fn spawn<F: IntoFuture + 'static>(future: F) -> Result<Task<F::Output>, ShutdownError> {
 	Lua::get(|lua| lua.async_runtime().spawn(future))
 }
 
-pub fn spawn_blocking<F, T>(func: F) -> Task<T>
+/// Spawns a task with a name, shown in the [`tasks`](AsyncRuntime::tasks) of the runtime.
+pub fn spawn_named<F: IntoFuture + 'static>(
+	name: impl Into<Cow<'static, str>>,
+	future: F,
+) -> Result<Task<F::Output>, ShutdownError> {
+	Lua::get(|lua| lua.async_runtime().spawn_named(name, future))
+}
+
+pub fn spawn_blocking<F, T>(func: F) -> Result<Task<T>, ShutdownError>
 where
 	F: FnOnce() -> T + Send + 'static,
 	T: Send + 'static,
@@ -21,11 +81,64 @@ where
 	Lua::get(|lua| lua.async_runtime().spawn_blocking(func))
 }
 
+/// Returns a token cancelled when the runtime starts shutting down.
+pub fn shutdown_token() -> CancellationToken {
+	Lua::get(|lua| lua.async_runtime().shutdown_token())
+}
+
+/// Returns a handle to run closures on the Lua thread from other threads.
+pub fn main_thread_handle() -> MainThreadHandle {
+	Lua::get(|lua| lua.async_runtime().main_thread_handle())
+}
+
+/// Awaits a future and converts its output to Lua values.
+async fn settle<F: Future<Output: FnReturn>>(future: F) -> Result<Tuple, Value> {
+	let output = future.await;
+	Lua::get(|lua| match output.fn_return(lua) {
+		Ok(Return::Values(values) | Return::Yield(values)) => {
+			Ok(values.into_iter().map(ToLua::to_lua).collect())
+		}
+		Err(err) => Err(err.to_lua()),
+	})
+}
+
+/// Reports an error that cannot be raised using [`ErrorNoHalt`](https://wiki.facepunch.com/gmod/Global.ErrorNoHalt).
+fn report_error(err: Value) {
+	match Table::globals().raw_get("ErrorNoHalt") {
+		Value::Function(error_no_halt) => {
+			let _ = error_no_halt.call([err, "\n".to_lua()]);
+		}
+		_ => crate::gm::print(&format!("{err:?}")),
+	}
+}
+
+/// What drives the async runtime, polling its tasks.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Driver {
+	/// Ticks on every [`Think`](https://wiki.facepunch.com/gmod/GM:Think) hook.
+	Think,
+	/// Ticks on every [`Tick`](https://wiki.facepunch.com/gmod/GM:Tick) hook.
+	Tick,
+	/// Ticks from a [`timer.Create`](https://wiki.facepunch.com/gmod/timer.Create) timer with no delay.
+	#[default]
+	Timer,
+	/// Only ticks when [`AsyncRuntime::tick`] is called.
+	Manual,
+}
+
 #[derive(Debug)]
 pub struct AsyncRuntime {
 	executor: Executor<'static>,
 	shutdown: Cell<bool>,
-	timers: TaskMap<()>,
+	shutting_down: Cell<bool>,
+	timers: time::Timers,
+	main_thread: MainThreadHandle,
+	driver: Cell<Option<Driver>>,
+	budget: Cell<Budget>,
+	meter: Rc<Meter>,
+	stats: Rc<Stats>,
+	grace_period: Cell<Duration>,
+	shutdown_token: CancellationToken,
 
 	#[cfg(feature = "tokio")]
 	tokio: TokioRuntime,
@@ -36,118 +149,562 @@ impl AsyncRuntime {
 		Self {
 			executor: Executor::new(),
 			shutdown: Cell::new(false),
-			timers: TaskMap::default(),
+			shutting_down: Cell::new(false),
+			timers: time::Timers::default(),
+			main_thread: MainThreadHandle::default(),
+			driver: Cell::new(None),
+			budget: Cell::new(Budget::unlimited()),
+			meter: Rc::default(),
+			stats: Rc::default(),
+			grace_period: Cell::new(Duration::ZERO),
+			shutdown_token: CancellationToken::new(),
 			#[cfg(feature = "tokio")]
 			tokio: TokioRuntime::new(),
 		}
 	}
 
-	pub(crate) fn tick(&self) {
+	/// Polls the ready tasks within the budget set with [`set_budget`](Self::set_budget).\
+	/// This is called by the driver, unless it is [`Driver::Manual`].
+	pub fn tick(&self) {
+		self.tick_with_budget(self.budget.get());
+	}
+
+	/// Polls the ready tasks within a budget, leaving the remaining ones to the next tick.
+	pub fn tick_with_budget(&self, budget: Budget) {
 		if !self.shutdown.get() {
+			let start = Instant::now();
+			self.meter.start(budget);
+			self.timers.fire();
+			Lua::get(|lua| self.main_thread.drain(lua, || self.meter.consume()));
 			self.executor.tick();
+			self.meter.end();
+			self.stats.tick(start.elapsed());
 		}
+
+		crate::gm::flush_pending();
+	}
+
+	/// Sets the budget used by [`tick`](Self::tick).
+	pub fn set_budget(&self, budget: Budget) {
+		self.budget.set(budget);
+	}
+
+	pub fn budget(&self) -> Budget {
+		self.budget.get()
+	}
+
+	/// Sets what drives the runtime, replacing the previous driver.
+	pub fn set_driver(&self, driver: Driver) -> Result<(), Value> {
+		static TICK: ffi::lua_CFunction = ffi::raw_function!(|state| unsafe {
+			Lua::enter(state, |lua| lua.async_runtime().tick());
+			0
+		});
+
+		if let Some(previous) = self.driver.take() {
+			self.remove_driver(previous);
+		}
+
+		let id = self.driver_id();
+		match driver {
+			Driver::Think | Driver::Tick => {
+				let add = library_function("hook", "Add")?;
+				let event = if driver == Driver::Think {
+					"Think"
+				} else {
+					"Tick"
+				};
+				call!(add: event, id, TICK)?;
+			}
+			Driver::Timer => {
+				let create = library_function("timer", "Create")?;
+				call!(create: id, 0.0, 0.0, TICK)?;
+			}
+			Driver::Manual => {}
+		}
+
+		self.driver.set(Some(driver));
+		Ok(())
+	}
+
+	/// The driver of the runtime, or `None` if it was never set or failed to be set.
+	pub fn driver(&self) -> Option<Driver> {
+		self.driver.get()
+	}
+
+	fn remove_driver(&self, driver: Driver) {
+		let id = self.driver_id();
+		let _ = match driver {
+			Driver::Think | Driver::Tick => library_function("hook", "Remove").and_then(|remove| {
+				let event = if driver == Driver::Think {
+					"Think"
+				} else {
+					"Tick"
+				};
+				call!(remove: event, id)
+			}),
+			Driver::Timer => {
+				library_function("timer", "Remove").and_then(|remove| call!(remove: id))
+			}
+			Driver::Manual => Ok(Tuple::new()),
+		};
+	}
+
+	fn driver_id(&self) -> String {
+		format!("__fg_poll_{:p}", self)
+	}
+
+	/// Sets how long tasks are given to complete when the module exits, before they are dropped.\
+	/// During this time, the runtime ticks without a budget and game time does not advance,
+	/// so only tasks that are ready or waiting for wall-clock timers can complete.
+	/// Tasks waiting for other threads, such as blocking closures, are not waited for.\
+	/// With the `tokio` feature, this also bounds how long the blocking threads of Tokio are waited for.\
+	/// The grace period ends early once no task is left, or none can make progress.
+	pub fn set_grace_period(&self, grace_period: Duration) {
+		self.grace_period.set(grace_period);
+	}
+
+	pub fn grace_period(&self) -> Duration {
+		self.grace_period.get()
+	}
+
+	/// Returns a token cancelled when the runtime starts shutting down,
+	/// letting tasks finish their work within the grace period.
+	///
+	/// Cancelling the returned token does not shut down the runtime.
+	pub fn shutdown_token(&self) -> CancellationToken {
+		self.shutdown_token.child_token()
 	}
 
 	pub(crate) fn shutdown(&self) {
-		if !self.shutdown.replace(true) {
-			self.executor.clear();
-			#[cfg(feature = "tokio")]
-			self.tokio.shutdown();
+		if self.shutting_down.replace(true) {
+			return;
+		}
+
+		if let Some(driver) = self.driver.take() {
+			self.remove_driver(driver);
+		}
+
+		self.shutdown_token.cancel();
+		let deadline = Instant::now() + self.grace_period.get();
+		while self.stats.alive() > 0 && Instant::now() < deadline {
+			self.tick_with_budget(Budget::unlimited());
+			if self.meter.polled() {
+				continue;
+			}
+
+			match self.timers.next_wall() {
+				Some(next) if next < deadline => {
+					std::thread::sleep(next.saturating_duration_since(Instant::now()));
+				}
+				_ => break,
+			}
 		}
+
+		self.shutdown.set(true);
+		self.main_thread.close();
+		self.executor.clear();
+		self.timers.clear();
+		promise::release();
+		#[cfg(feature = "tokio")]
+		self.tokio.shutdown(self.grace_period.get());
+	}
+
+	pub fn spawn<F: IntoFuture + 'static>(
+		&self,
+		future: F,
+	) -> Result<Task<F::Output>, ShutdownError> {
+		self.spawn_inner(None, future)
 	}
 
-	pub fn spawn<F: IntoFuture + 'static>(&self, future: F) -> Task<F::Output> {
+	/// Spawns a task with a name, shown in the [`tasks`](Self::tasks) of the runtime.
+	pub fn spawn_named<F: IntoFuture + 'static>(
+		&self,
+		name: impl Into<Cow<'static, str>>,
+		future: F,
+	) -> Result<Task<F::Output>, ShutdownError> {
+		self.spawn_inner(Some(name.into()), future)
+	}
+
+	fn spawn_inner<F: IntoFuture + 'static>(
+		&self,
+		name: Option<Cow<'static, str>>,
+		future: F,
+	) -> Result<Task<F::Output>, ShutdownError> {
 		if self.shutdown.get() {
-			panic!("Cannot spawn tasks on a shutdown runtime");
+			Err(ShutdownError)
 		} else {
-			self.executor.spawn(future)
+			let (meter, stats) = (self.meter.clone(), self.stats.clone());
+			let future = Budgeted::new(future.into_future(), meter, stats, name);
+			Ok(self.executor.spawn(future))
 		}
 	}
 
-	pub fn spawn_blocking<F, T>(&self, func: F) -> Task<T>
+	pub fn spawn_blocking<F, T>(&self, func: F) -> Result<Task<T>, ShutdownError>
 	where
 		F: FnOnce() -> T + Send + 'static,
 		T: Send + 'static,
 	{
-		let future = blocking(func);
-		self.spawn(future).detach()
+		let stats = self.stats.blocking();
+		let future = blocking(move || {
+			let _guard = BlockingGuard::new(stats);
+			func()
+		});
+
+		Ok(self.spawn(future)?.detach())
+	}
+
+	/// Returns a snapshot of the counters of the runtime.
+	pub fn metrics(&self) -> Metrics {
+		self.stats.metrics(self.timers.len())
+	}
+
+	/// Returns the tasks spawned with [`spawn_named`](Self::spawn_named) still running, from the oldest to the newest.
+	pub fn tasks(&self) -> Vec<TaskInfo> {
+		self.stats.tasks()
+	}
+
+	/// Returns a handle to run closures on the Lua thread from other threads.
+	pub fn main_thread_handle(&self) -> MainThreadHandle {
+		self.main_thread.clone()
+	}
+
+	/// Spawns the future of an async Lua function, reporting its error if it fails.
+	#[doc(hidden)]
+	pub fn __fg_spawn_detached<F>(&self, future: F) -> Result<(), Value>
+	where
+		F: Future<Output: FnReturn> + 'static,
+	{
+		self.spawn(async move {
+			if let Err(err) = settle(future).await {
+				report_error(err);
+			}
+		})?
+		.detach();
+
+		Ok(())
+	}
+
+	/// Spawns the future of an async Lua function, resuming the calling coroutine with its output.\
+	/// The caller must yield the returned values when this succeeds.
+	#[doc(hidden)]
+	pub fn __fg_spawn_yield<F>(&self, future: F) -> Result<Tuple, Value>
+	where
+		F: Future<Output: FnReturn> + 'static,
+	{
+		match Coroutine::running() {
+			None => Err("attempt to yield from outside a coroutine".to_lua()),
+			Some(cor) if is_task(&cor) => {
+				let promise = Promise::spawn(future)?;
+				Ok([promise.to_lua()].into_iter().collect())
+			}
+			Some(cor) => {
+				let suspension = Suspension::new(cor);
+				self.spawn(async move {
+					let res = settle(future).await;
+					suspension.resume(res);
+				})?
+				.detach();
+
+				Ok(Tuple::new())
+			}
+		}
+	}
+
+	/// Spawns the future of an async Lua function, calling `callback` with `true` followed by its values,
+	/// or `false` followed by its error.
+	#[doc(hidden)]
+	pub fn __fg_spawn_callback<F>(&self, future: F, callback: Function) -> Result<(), Value>
+	where
+		F: Future<Output: FnReturn> + 'static,
+	{
+		self.spawn(async move {
+			let res = match settle(future).await {
+				Ok(values) => callback.call([true.to_lua()].into_iter().chain(values)),
+				Err(err) => callback.call([false.to_lua(), err]),
+			};
+
+			if let Err(err) = res {
+				report_error(err);
+			}
+		})?
+		.detach();
+
+		Ok(())
+	}
+
+	/// Spawns the future of an async Lua function, returning a promise settled with its output.
+	#[doc(hidden)]
+	pub fn __fg_spawn_promise<F>(&self, future: F) -> Result<Promise, Value>
+	where
+		F: Future<Output: FnReturn> + 'static,
+	{
+		Ok(Promise::spawn(future)?)
 	}
 
 	#[cfg(feature = "tokio")]
 	pub fn tokio_handle(&self) -> &Handle {
 		self.tokio.handle()
 	}
+
+	/// The error that prevented the Tokio runtime from starting, reported when the module is loaded.
+	#[cfg(feature = "tokio")]
+	pub(crate) fn tokio_error(&self) -> Option<&str> {
+		self.tokio.error()
+	}
 }
 
 #[cfg(feature = "tokio")]
 use tokio_runtime::*;
 
+#[cfg(feature = "tokio")]
+pub use tokio_runtime::TokioBuilder;
+
+/// Sets the configuration of the Tokio runtime, before the Lua state is first entered.
+#[cfg(feature = "tokio")]
+#[doc(hidden)]
+pub fn __fg_configure_tokio(builder: TokioBuilder) {
+	TOKIO_BUILDER.set(Some(builder));
+}
+
 #[cfg(feature = "tokio")]
 mod tokio_runtime {
 	use futures_channel::oneshot::{Sender, channel};
-	use std::cell::RefCell;
+	use std::cell::{Cell, RefCell};
+	use std::io;
 	use std::thread::JoinHandle;
-	use tokio::runtime::Builder;
+	use std::time::Duration;
 	pub use tokio::runtime::Handle;
+	use tokio::runtime::{Builder, Runtime};
+
+	thread_local! {
+		pub static TOKIO_BUILDER: Cell<Option<TokioBuilder>> = const { Cell::new(None) };
+	}
+
+	/// The configuration of the Tokio runtime, set with the `tokio` argument of `#[flatgrass::entry]`.
+	///
+	/// By default, the runtime runs on a single background thread,
+	/// with the drivers of the `tokio-net` and `tokio-time` features enabled.
+	///
+	/// # Examples
+	///
+	/// ```
+	/// #[flatgrass::entry(tokio = flatgrass::task::TokioBuilder::multi_thread().worker_threads(4))]
+	/// pub fn entry() {
+	///   printfg!("Hello from binary module!");
+	/// }
+	/// ```
+	#[derive(Debug, Clone, PartialEq, Eq)]
+	pub struct TokioBuilder {
+		multi_thread: bool,
+		worker_threads: Option<usize>,
+		thread_name: Option<String>,
+		thread_stack_size: Option<usize>,
+		#[cfg(feature = "tokio-net")]
+		enable_io: bool,
+		#[cfg(feature = "tokio-time")]
+		enable_time: bool,
+	}
+
+	impl TokioBuilder {
+		/// A runtime running its tasks on a single background thread.
+		pub const fn current_thread() -> Self {
+			Self {
+				multi_thread: false,
+				worker_threads: None,
+				thread_name: None,
+				thread_stack_size: None,
+				#[cfg(feature = "tokio-net")]
+				enable_io: true,
+				#[cfg(feature = "tokio-time")]
+				enable_time: true,
+			}
+		}
+
+		/// A runtime running its tasks in parallel on a pool of worker threads.
+		pub const fn multi_thread() -> Self {
+			Self {
+				multi_thread: true,
+				worker_threads: None,
+				thread_name: None,
+				thread_stack_size: None,
+				#[cfg(feature = "tokio-net")]
+				enable_io: true,
+				#[cfg(feature = "tokio-time")]
+				enable_time: true,
+			}
+		}
+
+		/// Sets the number of worker threads of a multi-thread runtime, defaulting to the number of cores.
+		pub fn worker_threads(mut self, worker_threads: usize) -> Self {
+			self.worker_threads = Some(worker_threads);
+			self
+		}
+
+		/// Sets the name of the threads spawned by the runtime.
+		pub fn thread_name(mut self, thread_name: impl Into<String>) -> Self {
+			self.thread_name = Some(thread_name.into());
+			self
+		}
+
+		/// Sets the stack size of the threads spawned by the runtime, in bytes.
+		pub fn thread_stack_size(mut self, thread_stack_size: usize) -> Self {
+			self.thread_stack_size = Some(thread_stack_size);
+			self
+		}
+
+		/// Enables or disables the drivers of the `tokio-net` and `tokio-time` features.
+		#[cfg(any(feature = "tokio-net", feature = "tokio-time"))]
+		pub fn enable_drivers(mut self, enable_drivers: bool) -> Self {
+			#[cfg(feature = "tokio-net")]
+			{
+				self.enable_io = enable_drivers;
+			}
+
+			#[cfg(feature = "tokio-time")]
+			{
+				self.enable_time = enable_drivers;
+			}
+
+			self
+		}
+
+		/// Enables or disables the I/O driver, needed by `tokio::net`.
+		#[cfg(feature = "tokio-net")]
+		pub fn enable_io(mut self, enable_io: bool) -> Self {
+			self.enable_io = enable_io;
+			self
+		}
+
+		/// Enables or disables the time driver, needed by `tokio::time`.
+		#[cfg(feature = "tokio-time")]
+		pub fn enable_time(mut self, enable_time: bool) -> Self {
+			self.enable_time = enable_time;
+			self
+		}
+
+		fn build(&self) -> io::Result<Runtime> {
+			let mut builder = match self.multi_thread {
+				false => Builder::new_current_thread(),
+				true => Builder::new_multi_thread(),
+			};
+
+			if let Some(worker_threads) = self.worker_threads {
+				builder.worker_threads(worker_threads);
+			}
+
+			if let Some(thread_name) = &self.thread_name {
+				builder.thread_name(thread_name);
+			}
+
+			if let Some(thread_stack_size) = self.thread_stack_size {
+				builder.thread_stack_size(thread_stack_size);
+			}
+
+			#[cfg(feature = "tokio-net")]
+			if self.enable_io {
+				builder.enable_io();
+			}
+
+			#[cfg(feature = "tokio-time")]
+			if self.enable_time {
+				builder.enable_time();
+			}
+
+			builder.build()
+		}
+	}
+
+	impl Default for TokioBuilder {
+		fn default() -> Self {
+			Self::current_thread()
+		}
+	}
+
+	#[derive(Debug)]
+	enum Shutdown {
+		Thread(Sender<Duration>, JoinHandle<()>),
+		Runtime(Runtime),
+	}
 
 	#[derive(Debug)]
 	pub struct TokioRuntime {
-		shutdown: RefCell<Option<(Sender<()>, JoinHandle<()>)>>,
+		shutdown: RefCell<Option<Shutdown>>,
 		handle: Handle,
+		error: Option<String>,
 	}
 
 	impl TokioRuntime {
 		pub fn new() -> Self {
-			let tokio = Builder::new_current_thread()
-				.enable_all()
-				.build()
-				.expect("Failed to create Tokio runtime");
+			let config = TOKIO_BUILDER.take().unwrap_or_default();
+			Self::start(config).unwrap_or_else(Self::failed)
+		}
 
-			let (sender, receiver) = channel();
+		fn start(config: TokioBuilder) -> io::Result<Self> {
+			let tokio = config.build()?;
 			let handle = tokio.handle().clone();
-			let thread = std::thread::spawn(move || {
-				tokio.block_on(async move {
-					let _ = receiver.await;
-				});
-			});
+			let shutdown = match config.multi_thread {
+				true => Shutdown::Runtime(tokio),
+				false => {
+					let mut thread = std::thread::Builder::new();
+					if let Some(thread_name) = config.thread_name {
+						thread = thread.name(thread_name);
+					}
 
-			let shutdown = (sender, thread);
-			Self {
+					if let Some(thread_stack_size) = config.thread_stack_size {
+						thread = thread.stack_size(thread_stack_size);
+					}
+
+					let (sender, receiver) = channel();
+					let thread = thread.spawn(move || {
+						let timeout = tokio.block_on(receiver);
+						tokio.shutdown_timeout(timeout.unwrap_or_default());
+					})?;
+
+					Shutdown::Thread(sender, thread)
+				}
+			};
+
+			Ok(Self {
 				shutdown: RefCell::new(Some(shutdown)),
 				handle,
+				error: None,
+			})
+		}
+
+		/// A runtime without threads nor drivers, standing in for one that failed to start
+		/// so that the error can be reported when the module is loaded.
+		fn failed(err: io::Error) -> Self {
+			let tokio = Builder::new_current_thread()
+				.build()
+				.expect("a runtime without drivers cannot fail to build");
+
+			Self {
+				handle: tokio.handle().clone(),
+				shutdown: RefCell::new(Some(Shutdown::Runtime(tokio))),
+				error: Some(err.to_string()),
 			}
 		}
 
+		/// The error that prevented the runtime from starting.
+		pub fn error(&self) -> Option<&str> {
+			self.error.as_deref()
+		}
+
 		pub fn handle(&self) -> &Handle {
 			&self.handle
 		}
 
-		pub fn shutdown(&self) {
+		/// Shuts down the runtime, waiting up to the timeout for its blocking threads.
+		pub fn shutdown(&self, timeout: Duration) {
 			let shutdown = self.shutdown.borrow_mut().take();
-			if let Some((sender, thread)) = shutdown {
-				let _ = sender.send(());
-				let _ = thread.join();
+			match shutdown {
+				None => {}
+				Some(Shutdown::Runtime(tokio)) => tokio.shutdown_timeout(timeout),
+				Some(Shutdown::Thread(sender, thread)) => {
+					let _ = sender.send(timeout);
+					let _ = thread.join();
+				}
 			}
 		}
 	}
 }
-
-#[derive(Default, Debug)]
-struct TaskMap<T> {
-	tasks: RefCell<HashMap<LightUserdata, Box<Sender<T>>>>,
-}
-
-impl<T> TaskMap<T> {
-	pub fn insert(&self, sender: Sender<T>) -> LightUserdata {
-		let mut sender = Box::new(sender);
-		let ptr = (&raw mut *sender).cast();
-		self.tasks.borrow_mut().insert(ptr, sender);
-		ptr
-	}
-
-	pub fn wake(&self, ptr: LightUserdata, data: T) {
-		if let Some(sender) = self.tasks.borrow_mut().remove(&ptr) {
-			let _ = sender.send(data);
-		}
-	}
-}
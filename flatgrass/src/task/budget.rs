@@ -0,0 +1,273 @@
+use super::metrics::Stats;
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+
+/// Limits how much work a single tick of the async runtime can do.
+///
+/// Tasks that are ready once the budget is exhausted are polled on the next tick.\
+/// Closures queued with a [`MainThreadHandle`](super::MainThreadHandle) count as polls, and are kept for the next tick too.\
+/// At least one task or closure is run on every tick, so progress is always made.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Budget {
+	time: Option<Duration>,
+	polls: Option<usize>,
+}
+
+impl Budget {
+	/// A budget with no limits, running every ready task.
+	pub const fn unlimited() -> Self {
+		Self {
+			time: None,
+			polls: None,
+		}
+	}
+
+	/// Limits the time spent polling tasks in a tick.
+	pub const fn with_time(mut self, time: Duration) -> Self {
+		self.time = Some(time);
+		self
+	}
+
+	/// Limits the number of times tasks are polled in a tick.
+	pub const fn with_polls(mut self, polls: usize) -> Self {
+		self.polls = Some(polls);
+		self
+	}
+
+	pub const fn time(&self) -> Option<Duration> {
+		self.time
+	}
+
+	pub const fn polls(&self) -> Option<usize> {
+		self.polls
+	}
+}
+
+/// Tracks the budget of the current tick.
+#[derive(Debug, Default)]
+pub(super) struct Meter {
+	deadline: Cell<Option<Instant>>,
+	polls_left: Cell<Option<usize>>,
+	polled: Cell<bool>,
+	deferred: RefCell<Vec<Waker>>,
+}
+
+impl Meter {
+	/// Starts a tick, waking the tasks deferred by the previous one.
+	pub fn start(&self, budget: Budget) {
+		self.deadline
+			.set(budget.time.map(|time| Instant::now() + time));
+		self.polls_left.set(budget.polls);
+		self.polled.set(false);
+
+		let deferred = std::mem::take(&mut *self.deferred.borrow_mut());
+		for waker in deferred {
+			waker.wake();
+		}
+	}
+
+	/// Ends a tick, lifting the limits until the next one.
+	pub fn end(&self) {
+		self.deadline.set(None);
+		self.polls_left.set(None);
+	}
+
+	/// Returns `true` if a task can be polled, consuming part of the budget.
+	pub fn consume(&self) -> bool {
+		if self.polled.replace(true) {
+			if self.polls_left.get() == Some(0) {
+				return false;
+			}
+
+			if let Some(deadline) = self.deadline.get()
+				&& Instant::now() >= deadline
+			{
+				return false;
+			}
+		}
+
+		if let Some(polls) = self.polls_left.get() {
+			self.polls_left.set(Some(polls.saturating_sub(1)));
+		}
+
+		true
+	}
+
+	/// Returns `true` if a task was polled since the tick started.
+	pub fn polled(&self) -> bool {
+		self.polled.get()
+	}
+
+	fn defer(&self, waker: &Waker) {
+		self.deferred.borrow_mut().push(waker.clone());
+	}
+}
+
+/// A future that is only polled while the budget of the tick allows it, recording its statistics.
+pub(super) struct Budgeted<F> {
+	future: F,
+	meter: Rc<Meter>,
+	stats: Rc<Stats>,
+	id: Option<u64>,
+}
+
+impl<F> Budgeted<F> {
+	pub fn new(
+		future: F,
+		meter: Rc<Meter>,
+		stats: Rc<Stats>,
+		name: Option<Cow<'static, str>>,
+	) -> Self {
+		let id = stats.spawn(name);
+		Self {
+			future,
+			meter,
+			stats,
+			id,
+		}
+	}
+}
+
+impl<F> Drop for Budgeted<F> {
+	fn drop(&mut self) {
+		self.stats.release(self.id);
+	}
+}
+
+impl<F: Future> Future for Budgeted<F> {
+	type Output = F::Output;
+
+	fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+		// SAFETY: the future is never moved out of the pinned wrapper.
+		let this = unsafe { self.get_unchecked_mut() };
+		if !this.meter.consume() {
+			this.meter.defer(cx.waker());
+			return Poll::Pending;
+		}
+
+		let start = this.id.map(|_| Instant::now());
+		let poll = unsafe { Pin::new_unchecked(&mut this.future) }.poll(cx);
+		if let (Some(id), Some(start)) = (this.id, start) {
+			this.stats.poll(id, start.elapsed());
+		}
+
+		if poll.is_ready() {
+			this.stats.complete();
+		}
+
+		poll
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::sync::Arc;
+	use std::sync::atomic::{AtomicUsize, Ordering};
+	use std::task::Wake;
+
+	#[derive(Default)]
+	struct Counter(AtomicUsize);
+
+	impl Wake for Counter {
+		fn wake(self: Arc<Self>) {
+			self.0.fetch_add(1, Ordering::Relaxed);
+		}
+	}
+
+	#[test]
+	fn budget_limits() {
+		assert_eq!(Budget::default(), Budget::unlimited());
+		let budget = Budget::unlimited()
+			.with_time(Duration::from_millis(2))
+			.with_polls(3);
+		assert_eq!(budget.time(), Some(Duration::from_millis(2)));
+		assert_eq!(budget.polls(), Some(3));
+		assert_eq!(Budget::unlimited().time(), None);
+		assert_eq!(Budget::unlimited().polls(), None);
+	}
+
+	#[test]
+	fn meter_polls() {
+		let meter = Meter::default();
+		meter.start(Budget::unlimited().with_polls(2));
+		assert!(meter.consume());
+		assert!(meter.consume());
+		assert!(!meter.consume());
+
+		meter.end();
+		assert!(meter.consume());
+	}
+
+	#[test]
+	fn meter_always_polls_once() {
+		let meter = Meter::default();
+		meter.start(Budget::unlimited().with_polls(0));
+		assert!(meter.consume());
+		assert!(!meter.consume());
+
+		meter.start(Budget::unlimited().with_time(Duration::ZERO));
+		assert!(meter.consume());
+		assert!(!meter.consume());
+	}
+
+	#[test]
+	fn meter_unlimited() {
+		let meter = Meter::default();
+		meter.start(Budget::unlimited());
+		assert!((0..1000).all(|_| meter.consume()));
+	}
+
+	#[test]
+	fn meter_wakes_deferred() {
+		let meter = Meter::default();
+		let counter = Arc::new(Counter::default());
+		let waker = Waker::from(counter.clone());
+		meter.defer(&waker);
+		meter.defer(&waker);
+		assert_eq!(counter.0.load(Ordering::Relaxed), 0);
+
+		meter.start(Budget::unlimited());
+		assert_eq!(counter.0.load(Ordering::Relaxed), 2);
+		meter.start(Budget::unlimited());
+		assert_eq!(counter.0.load(Ordering::Relaxed), 2);
+	}
+
+	#[test]
+	fn budgeted_defers_when_exhausted() {
+		let meter = Rc::new(Meter::default());
+		let stats = Rc::new(Stats::default());
+		let counter = Arc::new(Counter::default());
+		let waker = Waker::from(counter.clone());
+		let mut cx = Context::from_waker(&waker);
+
+		let mut first = std::pin::pin!(Budgeted::new(
+			std::future::ready(1),
+			meter.clone(),
+			stats.clone(),
+			None,
+		));
+		let mut second = std::pin::pin!(Budgeted::new(
+			std::future::ready(2),
+			meter.clone(),
+			stats.clone(),
+			Some("second".into()),
+		));
+
+		meter.start(Budget::unlimited().with_polls(1));
+		assert_eq!(first.as_mut().poll(&mut cx), Poll::Ready(1));
+		assert_eq!(second.as_mut().poll(&mut cx), Poll::Pending);
+		assert_eq!(stats.metrics(0).completed, 1);
+		assert_eq!(stats.alive(), 2);
+
+		meter.start(Budget::unlimited().with_polls(1));
+		assert_eq!(counter.0.load(Ordering::Relaxed), 1);
+		assert_eq!(second.as_mut().poll(&mut cx), Poll::Ready(2));
+		assert_eq!(stats.metrics(0).completed, 2);
+		assert_eq!(stats.tasks()[0].polls, 1);
+	}
+}
@@ -0,0 +1,219 @@
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
+use std::task::{Context, Poll, Waker};
+
+#[derive(Debug, Default)]
+struct Inner {
+	cancelled: AtomicBool,
+	waiting: Mutex<Waiting>,
+}
+
+#[derive(Debug, Default)]
+struct Waiting {
+	wakers: Vec<Waker>,
+	children: Vec<Weak<Inner>>,
+}
+
+impl Inner {
+	fn waiting(&self) -> MutexGuard<'_, Waiting> {
+		self.waiting.lock().unwrap_or_else(|err| err.into_inner())
+	}
+
+	fn cancel(&self) {
+		let waiting = {
+			let mut waiting = self.waiting();
+			if self.cancelled.swap(true, Ordering::AcqRel) {
+				return;
+			}
+
+			std::mem::take(&mut *waiting)
+		};
+
+		for waker in waiting.wakers {
+			waker.wake();
+		}
+
+		for child in waiting.children.iter().filter_map(Weak::upgrade) {
+			child.cancel();
+		}
+	}
+}
+
+/// A token to cooperatively cancel tasks, which can be shared between threads.
+///
+/// Cancelling a token also cancels the tokens created with [`child_token`](Self::child_token).
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+	inner: Arc<Inner>,
+}
+
+impl CancellationToken {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Creates a token cancelled along with this one, but that can be cancelled on its own.
+	pub fn child_token(&self) -> Self {
+		let child = Self::new();
+		let mut waiting = self.inner.waiting();
+		if self.is_cancelled() {
+			child.inner.cancelled.store(true, Ordering::Release);
+		} else {
+			waiting.children.retain(|child| child.strong_count() > 0);
+			waiting.children.push(Arc::downgrade(&child.inner));
+		}
+
+		child
+	}
+
+	/// Cancels the token and its children, waking the tasks waiting for it.
+	pub fn cancel(&self) {
+		self.inner.cancel();
+	}
+
+	pub fn is_cancelled(&self) -> bool {
+		self.inner.cancelled.load(Ordering::Acquire)
+	}
+
+	/// Waits for the token to be cancelled.
+	pub fn cancelled(&self) -> Cancelled {
+		Cancelled {
+			token: self.clone(),
+		}
+	}
+
+	/// Runs a future until it completes, or returns `None` if the token is cancelled first.
+	pub async fn run_until_cancelled<F: IntoFuture>(&self, future: F) -> Option<F::Output> {
+		let mut future = std::pin::pin!(future.into_future());
+		let mut cancelled = self.cancelled();
+		std::future::poll_fn(|cx| {
+			if Pin::new(&mut cancelled).poll(cx).is_ready() {
+				Poll::Ready(None)
+			} else {
+				future.as_mut().poll(cx).map(Some)
+			}
+		})
+		.await
+	}
+}
+
+/// A future completing once a [`CancellationToken`] is cancelled.
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Cancelled {
+	token: CancellationToken,
+}
+
+impl Future for Cancelled {
+	type Output = ();
+
+	fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+		let mut waiting = self.token.inner.waiting();
+		if self.token.is_cancelled() {
+			Poll::Ready(())
+		} else {
+			if !waiting
+				.wakers
+				.iter()
+				.any(|waker| waker.will_wake(cx.waker()))
+			{
+				waiting.wakers.push(cx.waker().clone());
+			}
+
+			Poll::Pending
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::pin::pin;
+	use std::sync::atomic::AtomicUsize;
+	use std::task::Wake;
+
+	#[derive(Default)]
+	struct Counter(AtomicUsize);
+
+	impl Wake for Counter {
+		fn wake(self: Arc<Self>) {
+			self.0.fetch_add(1, Ordering::Relaxed);
+		}
+	}
+
+	#[test]
+	fn cancel() {
+		let token = CancellationToken::new();
+		let clone = token.clone();
+		assert!(!token.is_cancelled());
+
+		clone.cancel();
+		assert!(token.is_cancelled());
+		token.cancel();
+		assert!(clone.is_cancelled());
+	}
+
+	#[test]
+	fn children() {
+		let parent = CancellationToken::new();
+		let child = parent.child_token();
+		let grandchild = child.child_token();
+		let sibling = parent.child_token();
+
+		sibling.cancel();
+		assert!(!parent.is_cancelled());
+		assert!(!child.is_cancelled());
+
+		parent.cancel();
+		assert!(child.is_cancelled());
+		assert!(grandchild.is_cancelled());
+		assert!(parent.child_token().is_cancelled());
+	}
+
+	#[test]
+	fn dropped_children() {
+		let parent = CancellationToken::new();
+		for _ in 0..10 {
+			drop(parent.child_token());
+		}
+
+		let child = parent.child_token();
+		assert_eq!(parent.inner.waiting().children.len(), 1);
+		parent.cancel();
+		assert!(child.is_cancelled());
+	}
+
+	#[test]
+	fn cancelled_wakes() {
+		let token = CancellationToken::new();
+		let counter = Arc::new(Counter::default());
+		let waker = Waker::from(counter.clone());
+		let mut cx = Context::from_waker(&waker);
+
+		let mut cancelled = pin!(token.child_token().cancelled());
+		assert_eq!(cancelled.as_mut().poll(&mut cx), Poll::Pending);
+		assert_eq!(cancelled.as_mut().poll(&mut cx), Poll::Pending);
+		assert_eq!(counter.0.load(Ordering::Relaxed), 0);
+
+		token.cancel();
+		assert_eq!(counter.0.load(Ordering::Relaxed), 1);
+		assert_eq!(cancelled.as_mut().poll(&mut cx), Poll::Ready(()));
+	}
+
+	#[test]
+	fn run_until_cancelled() {
+		let mut cx = Context::from_waker(Waker::noop());
+		let token = CancellationToken::new();
+		let mut ready = pin!(token.run_until_cancelled(std::future::ready(1)));
+		assert_eq!(ready.as_mut().poll(&mut cx), Poll::Ready(Some(1)));
+
+		let mut pending = pin!(token.run_until_cancelled(std::future::pending::<()>()));
+		assert_eq!(pending.as_mut().poll(&mut cx), Poll::Pending);
+		token.cancel();
+		assert_eq!(pending.as_mut().poll(&mut cx), Poll::Ready(None));
+
+		let mut ready = pin!(token.run_until_cancelled(std::future::ready(1)));
+		assert_eq!(ready.as_mut().poll(&mut cx), Poll::Ready(None));
+	}
+}
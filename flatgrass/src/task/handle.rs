@@ -0,0 +1,164 @@
+use crate::lua::Lua;
+use futures_channel::oneshot::channel;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+type Job = Box<dyn FnOnce(&Lua) + Send>;
+
+#[derive(Default)]
+struct Queue {
+	jobs: Mutex<Vec<Job>>,
+	closed: AtomicBool,
+}
+
+/// A handle to run closures on the Lua thread, from any thread.
+///
+/// Closures are queued and run in order the next time the async runtime ticks.\
+/// Once the Lua state is closed, queued closures are dropped without being run.
+#[derive(Clone, Default)]
+pub struct MainThreadHandle {
+	queue: Arc<Queue>,
+}
+
+impl MainThreadHandle {
+	/// Queues a closure to run on the Lua thread.
+	///
+	/// Returns `false` if the Lua state was closed, in which case the closure is dropped.
+	pub fn run<F>(&self, func: F) -> bool
+	where
+		F: FnOnce(&Lua) + Send + 'static,
+	{
+		let mut jobs = self
+			.queue
+			.jobs
+			.lock()
+			.unwrap_or_else(|err| err.into_inner());
+		if self.queue.closed.load(Ordering::Acquire) {
+			false
+		} else {
+			jobs.push(Box::new(func));
+			true
+		}
+	}
+
+	/// Queues a closure to run on the Lua thread, returning a future resolving to its output.
+	///
+	/// # Panics
+	///
+	/// The future panics if the Lua state is closed before the closure runs.
+	pub fn run_async<F, T>(&self, func: F) -> impl Future<Output = T> + Send + 'static
+	where
+		F: FnOnce(&Lua) -> T + Send + 'static,
+		T: Send + 'static,
+	{
+		let (sender, receiver) = channel();
+		self.run(move |lua| {
+			let _ = sender.send(func(lua));
+		});
+
+		async move {
+			match receiver.await {
+				Ok(output) => output,
+				Err(_) => panic!("the Lua state was closed before the closure could run"),
+			}
+		}
+	}
+
+	/// Returns `true` if the Lua state was closed.
+	pub fn is_closed(&self) -> bool {
+		self.queue.closed.load(Ordering::Acquire)
+	}
+
+	/// Runs the queued closures, including those queued while draining, while `proceed` returns `true`.
+	///
+	/// The closures left over are kept in order, to run on the next drain.
+	pub(super) fn drain(&self, lua: &Lua, mut proceed: impl FnMut() -> bool) {
+		loop {
+			let mut jobs = std::mem::take(
+				&mut *self
+					.queue
+					.jobs
+					.lock()
+					.unwrap_or_else(|err| err.into_inner()),
+			)
+			.into_iter();
+			if jobs.len() == 0 {
+				break;
+			}
+
+			while let Some(job) = jobs.next() {
+				if proceed() {
+					job(lua);
+				} else {
+					let mut queued = self
+						.queue
+						.jobs
+						.lock()
+						.unwrap_or_else(|err| err.into_inner());
+					if !self.queue.closed.load(Ordering::Acquire) {
+						queued.splice(0..0, std::iter::once(job).chain(jobs));
+					}
+					return;
+				}
+			}
+		}
+	}
+
+	/// Drops the queued closures and rejects new ones.
+	pub(super) fn close(&self) {
+		let mut jobs = self
+			.queue
+			.jobs
+			.lock()
+			.unwrap_or_else(|err| err.into_inner());
+		self.queue.closed.store(true, Ordering::Release);
+		jobs.clear();
+	}
+}
+
+impl std::fmt::Debug for MainThreadHandle {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		f.debug_struct("MainThreadHandle")
+			.field("closed", &self.is_closed())
+			.finish_non_exhaustive()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::pin::pin;
+	use std::task::{Context, Poll, Waker};
+
+	#[test]
+	fn run_until_closed() {
+		let handle = MainThreadHandle::default();
+		assert!(!handle.is_closed());
+		assert!(handle.run(|_| {}));
+		assert!(handle.clone().run(|_| {}));
+		assert_eq!(handle.queue.jobs.lock().unwrap().len(), 2);
+
+		handle.close();
+		assert!(handle.is_closed());
+		assert!(!handle.run(|_| {}));
+		assert!(handle.queue.jobs.lock().unwrap().is_empty());
+	}
+
+	#[test]
+	#[should_panic = "the Lua state was closed"]
+	fn run_async_after_close() {
+		let handle = MainThreadHandle::default();
+		let mut future = pin!(handle.run_async(|_| 1));
+		assert_eq!(
+			future
+				.as_mut()
+				.poll(&mut Context::from_waker(Waker::noop())),
+			Poll::Pending
+		);
+
+		handle.close();
+		let _ = future
+			.as_mut()
+			.poll(&mut Context::from_waker(Waker::noop()));
+	}
+}
@@ -0,0 +1,154 @@
+use super::{ShutdownError, Task};
+use crate::lua::Lua;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// A group of tasks spawned on the async runtime, awaited in the order they complete.
+///
+/// Dropping the set cancels the tasks still running.
+#[derive(Debug)]
+pub struct JoinSet<T> {
+	tasks: Vec<Task<T>>,
+}
+
+impl<T: 'static> JoinSet<T> {
+	pub const fn new() -> Self {
+		Self { tasks: Vec::new() }
+	}
+
+	/// Spawns a task in the set.
+	pub fn spawn<F>(&mut self, future: F) -> Result<(), ShutdownError>
+	where
+		F: IntoFuture<Output = T> + 'static,
+	{
+		let task = Lua::get(|lua| lua.async_runtime().spawn(future))?;
+		self.tasks.push(task);
+		Ok(())
+	}
+
+	/// Spawns a blocking closure in the set, see [`spawn_blocking`](super::spawn_blocking).
+	pub fn spawn_blocking<F>(&mut self, func: F) -> Result<(), ShutdownError>
+	where
+		F: FnOnce() -> T + Send + 'static,
+		T: Send,
+	{
+		let task = Lua::get(|lua| lua.async_runtime().spawn_blocking(func))?;
+		self.tasks.push(task);
+		Ok(())
+	}
+
+	/// Waits for the next task to complete, or returns `None` if the set is empty.
+	pub async fn join_next(&mut self) -> Option<T> {
+		std::future::poll_fn(|cx| self.poll_join_next(cx)).await
+	}
+
+	/// Waits for every task to complete, returning their outputs in the order they completed.
+	pub async fn join_all(mut self) -> Vec<T> {
+		let mut outputs = Vec::with_capacity(self.len());
+		while let Some(output) = self.join_next().await {
+			outputs.push(output);
+		}
+
+		outputs
+	}
+
+	/// Polls the tasks, removing and returning the output of the first one that completed.
+	pub fn poll_join_next(&mut self, cx: &mut Context<'_>) -> Poll<Option<T>> {
+		if self.tasks.is_empty() {
+			return Poll::Ready(None);
+		}
+
+		for i in 0..self.tasks.len() {
+			if let Poll::Ready(output) = Pin::new(&mut self.tasks[i]).poll(cx) {
+				self.tasks.swap_remove(i);
+				return Poll::Ready(Some(output));
+			}
+		}
+
+		Poll::Pending
+	}
+
+	/// Cancels every task in the set.
+	pub fn abort_all(&mut self) {
+		self.tasks.clear();
+	}
+
+	/// Removes every task from the set, letting them run in the background.
+	pub fn detach_all(&mut self) {
+		for task in self.tasks.drain(..) {
+			task.detach();
+		}
+	}
+
+	pub fn len(&self) -> usize {
+		self.tasks.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.tasks.is_empty()
+	}
+}
+
+impl<T: 'static> Default for JoinSet<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use avenir::Executor;
+	use std::task::Waker;
+
+	fn join_set<T>(executor: &Executor<'static>, outputs: Vec<T>) -> JoinSet<T>
+	where
+		T: 'static,
+	{
+		let tasks = outputs
+			.into_iter()
+			.map(|output| executor.spawn(std::future::ready(output)))
+			.collect();
+
+		JoinSet { tasks }
+	}
+
+	#[test]
+	fn empty() {
+		let mut set = JoinSet::<()>::default();
+		let mut cx = Context::from_waker(Waker::noop());
+		assert!(set.is_empty());
+		assert_eq!(set.poll_join_next(&mut cx), Poll::Ready(None));
+	}
+
+	#[test]
+	fn join_next() {
+		let executor = Executor::new();
+		let mut set = join_set(&executor, vec![1, 2, 3]);
+		let mut cx = Context::from_waker(Waker::noop());
+		assert_eq!(set.len(), 3);
+		assert_eq!(set.poll_join_next(&mut cx), Poll::Pending);
+
+		executor.tick();
+		let mut outputs = Vec::new();
+		while let Poll::Ready(Some(output)) = set.poll_join_next(&mut cx) {
+			outputs.push(output);
+		}
+
+		outputs.sort_unstable();
+		assert_eq!(outputs, [1, 2, 3]);
+		assert!(set.is_empty());
+	}
+
+	#[test]
+	fn abort_and_detach() {
+		let executor = Executor::new();
+		let mut set = join_set(&executor, vec![1, 2]);
+		set.abort_all();
+		assert!(set.is_empty());
+
+		let mut set = join_set(&executor, vec![3]);
+		set.detach_all();
+		assert!(set.is_empty());
+	}
+}
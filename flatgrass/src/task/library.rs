@@ -0,0 +1,74 @@
+//! A Lua library to use the async runtime from Lua scripts.
+//!
+//! Register it from the entry point of your module:
+//! ```
+//! #[flatgrass::entry]
+//! pub fn entry() {
+//!   flatgrass::task::library::register("async");
+//! }
+//! ```
+//!
+//! Lua code can then run functions as tasks, and wait without blocking the game:
+//! ```lua
+//! async.spawn(function(name)
+//!   async.sleep(1)
+//!   local body, err = async.await(http_promise)
+//!   print(name, body or err)
+//! end, "request"):Catch(print)
+//! ```
+
+use super::{Promise, game_time_tag, is_task, run_coroutine};
+use crate::lua::FnReturn;
+use crate::lua::table;
+use crate::lua::util::{Tuple, Yield};
+use crate::prelude::*;
+
+/// Creates the table of the library.
+///
+/// - `spawn(func, ...)` runs a function as a task, returning a promise settled with its return values.
+/// - `await(value)` waits for a promise, a number of seconds or a function called with a resume callback.
+/// - `sleep(seconds)` and `sleep_curtime(seconds)` wait in real time and game time.
+/// - `yield()` waits for the next tick of the runtime.
+///
+/// Every function but `spawn` has to be called from a task.
+pub fn table() -> Table {
+	let spawn = Function::from_fn(|(func, args): (Function, Tuple)| {
+		let cor = Coroutine::from_function(&func);
+		Promise::spawn(run_coroutine(cor, args)).map_err(Value::from)
+	});
+
+	let wait = Function::from_fn(|value: Value| await_value(value));
+	let sleep = Function::from_fn(|secs: f64| await_value(secs.to_lua()));
+	let sleep_curtime = Function::from_fn(|secs: f64| await_value((game_time_tag(), secs)));
+
+	let yield_now = Function::from_fn(|()| await_value(Value::Nil));
+	table! {
+		spawn: spawn,
+		await: wait,
+		sleep: sleep,
+		sleep_curtime: sleep_curtime,
+		yield: yield_now,
+	}
+}
+
+/// Registers the library as a global, and as the value returned by `require`.
+pub fn register(name: &str) -> Table {
+	let table = table();
+	let globals = Table::globals();
+	globals.raw_set(name, &table);
+	if let Value::Table(package) = globals.raw_get("package")
+		&& let Value::Table(loaded) = package.raw_get("loaded")
+	{
+		loaded.raw_set(name, &table);
+	}
+
+	table
+}
+
+/// Yields values to the task running the current coroutine.
+fn await_value<T: FnReturn>(value: T) -> Result<Yield<T>, Value> {
+	match Coroutine::running() {
+		Some(cor) if is_task(&cor) => Ok(Yield(value)),
+		_ => Err("attempt to await outside of a task".to_lua()),
+	}
+}
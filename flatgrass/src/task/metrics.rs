@@ -0,0 +1,350 @@
+use crate::gm::{self, concommand};
+use crate::lua::{Lua, Value};
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// A snapshot of the counters of the async runtime.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct Metrics {
+	/// The number of tasks spawned.
+	pub spawned: u64,
+	/// The number of tasks that completed.
+	pub completed: u64,
+	/// The number of tasks that have not completed or been cancelled.
+	pub pending: usize,
+	/// The number of closures spawned on the blocking pool.
+	pub blocking_spawned: u64,
+	/// The number of closures currently running on the blocking pool.
+	pub blocking_active: usize,
+	/// The number of times the runtime ticked.
+	pub ticks: u64,
+	/// The time spent in the last tick.
+	pub last_tick: Duration,
+	/// The longest time spent in a tick.
+	pub max_tick: Duration,
+	/// The number of timers waiting to fire, including tasks that yielded.
+	pub timers: usize,
+}
+
+/// Information about a task spawned with [`spawn_named`](super::spawn_named).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TaskInfo {
+	pub name: Cow<'static, str>,
+	/// The time since the task was spawned.
+	pub age: Duration,
+	/// The time spent polling the task.
+	pub busy: Duration,
+	/// The number of times the task was polled.
+	pub polls: u64,
+}
+
+#[derive(Debug)]
+struct Named {
+	name: Cow<'static, str>,
+	spawned_at: Instant,
+	busy: Duration,
+	polls: u64,
+}
+
+/// Counters of the blocking pool, updated from worker threads.
+#[derive(Debug, Default)]
+pub(super) struct BlockingStats {
+	spawned: AtomicU64,
+	active: AtomicUsize,
+}
+
+/// Decrements the number of active blocking closures when dropped.
+pub(super) struct BlockingGuard(Arc<BlockingStats>);
+
+impl BlockingGuard {
+	pub fn new(stats: Arc<BlockingStats>) -> Self {
+		stats.active.fetch_add(1, Ordering::Relaxed);
+		Self(stats)
+	}
+}
+
+impl Drop for BlockingGuard {
+	fn drop(&mut self) {
+		self.0.active.fetch_sub(1, Ordering::Relaxed);
+	}
+}
+
+/// The counters of the async runtime.
+#[derive(Debug, Default)]
+pub(super) struct Stats {
+	spawned: Cell<u64>,
+	completed: Cell<u64>,
+	alive: Cell<usize>,
+	ticks: Cell<u64>,
+	last_tick: Cell<Duration>,
+	max_tick: Cell<Duration>,
+	next_id: Cell<u64>,
+	named: RefCell<HashMap<u64, Named>>,
+	blocking: Arc<BlockingStats>,
+}
+
+impl Stats {
+	/// Records a spawned task, returning an id if it is named.
+	pub fn spawn(&self, name: Option<Cow<'static, str>>) -> Option<u64> {
+		self.spawned.set(self.spawned.get() + 1);
+		self.alive.set(self.alive.get() + 1);
+		name.map(|name| {
+			let id = self.next_id.get();
+			self.next_id.set(id + 1);
+			self.named.borrow_mut().insert(
+				id,
+				Named {
+					name,
+					spawned_at: Instant::now(),
+					busy: Duration::ZERO,
+					polls: 0,
+				},
+			);
+
+			id
+		})
+	}
+
+	/// Records a poll of a named task.
+	pub fn poll(&self, id: u64, busy: Duration) {
+		if let Some(named) = self.named.borrow_mut().get_mut(&id) {
+			named.busy += busy;
+			named.polls += 1;
+		}
+	}
+
+	pub fn complete(&self) {
+		self.completed.set(self.completed.get() + 1);
+	}
+
+	/// Records a task being dropped, after completing or being cancelled.
+	pub fn release(&self, id: Option<u64>) {
+		self.alive.set(self.alive.get() - 1);
+		if let Some(id) = id {
+			self.named.borrow_mut().remove(&id);
+		}
+	}
+
+	pub fn tick(&self, elapsed: Duration) {
+		self.ticks.set(self.ticks.get() + 1);
+		self.last_tick.set(elapsed);
+		self.max_tick.set(self.max_tick.get().max(elapsed));
+	}
+
+	/// Records a closure spawned on the blocking pool.
+	pub fn blocking(&self) -> Arc<BlockingStats> {
+		self.blocking.spawned.fetch_add(1, Ordering::Relaxed);
+		self.blocking.clone()
+	}
+
+	pub fn alive(&self) -> usize {
+		self.alive.get()
+	}
+
+	pub fn metrics(&self, timers: usize) -> Metrics {
+		Metrics {
+			spawned: self.spawned.get(),
+			completed: self.completed.get(),
+			pending: self.alive.get(),
+			blocking_spawned: self.blocking.spawned.load(Ordering::Relaxed),
+			blocking_active: self.blocking.active.load(Ordering::Relaxed),
+			ticks: self.ticks.get(),
+			last_tick: self.last_tick.get(),
+			max_tick: self.max_tick.get(),
+			timers,
+		}
+	}
+
+	/// The named tasks still running, from the oldest to the newest.
+	pub fn tasks(&self) -> Vec<TaskInfo> {
+		let now = Instant::now();
+		let named = self.named.borrow();
+		let mut tasks: Vec<TaskInfo> = named
+			.values()
+			.map(|named| TaskInfo {
+				name: named.name.clone(),
+				age: now.saturating_duration_since(named.spawned_at),
+				busy: named.busy,
+				polls: named.polls,
+			})
+			.collect();
+
+		tasks.sort_by_key(|task| std::cmp::Reverse(task.age));
+		tasks
+	}
+}
+
+/// Adds a console command printing the metrics of the async runtime and its oldest named tasks.\
+/// Players can only run it if they are super admins.
+pub fn add_command(name: &str) -> Result<(), Value> {
+	concommand::add(name, |ply, _, args, _| {
+		if ply
+			.as_ref()
+			.is_some_and(|ply| !ply.is_super_admin().unwrap_or(false))
+		{
+			return;
+		}
+
+		let limit = args.first().and_then(|arg| arg.parse().ok()).unwrap_or(10);
+		let report = Lua::get(|lua| {
+			let runtime = lua.async_runtime();
+			report(&runtime.metrics(), &runtime.tasks(), limit)
+		});
+
+		match ply {
+			Some(ply) => {
+				for line in report.lines() {
+					let _ = ply.chat_print(line);
+				}
+			}
+			None => gm::print(report.trim_end()),
+		}
+	})
+}
+
+fn report(metrics: &Metrics, tasks: &[TaskInfo], limit: usize) -> String {
+	let mut report = String::new();
+	let _ = writeln!(
+		report,
+		"tasks: {} pending, {} spawned, {} completed",
+		metrics.pending, metrics.spawned, metrics.completed
+	);
+	let _ = writeln!(
+		report,
+		"blocking: {} active, {} spawned",
+		metrics.blocking_active, metrics.blocking_spawned
+	);
+	let _ = writeln!(
+		report,
+		"ticks: {}, last {:.2?}, max {:.2?}",
+		metrics.ticks, metrics.last_tick, metrics.max_tick
+	);
+	let _ = writeln!(report, "timers: {}", metrics.timers);
+
+	if !tasks.is_empty() {
+		let _ = writeln!(report, "oldest tasks:");
+		for task in tasks.iter().take(limit) {
+			let _ = writeln!(
+				report,
+				"  {}: {:.2?} (busy {:.2?}, {} polls)",
+				task.name, task.age, task.busy, task.polls
+			);
+		}
+	}
+
+	report
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn counters() {
+		let stats = Stats::default();
+		let first = stats.spawn(None);
+		let second = stats.spawn(Some("second".into()));
+		assert_eq!(first, None);
+		assert!(second.is_some());
+		assert_eq!(stats.alive(), 2);
+
+		stats.complete();
+		stats.release(first);
+		stats.tick(Duration::from_millis(3));
+		stats.tick(Duration::from_millis(1));
+
+		let metrics = stats.metrics(4);
+		assert_eq!(metrics.spawned, 2);
+		assert_eq!(metrics.completed, 1);
+		assert_eq!(metrics.pending, 1);
+		assert_eq!(metrics.ticks, 2);
+		assert_eq!(metrics.last_tick, Duration::from_millis(1));
+		assert_eq!(metrics.max_tick, Duration::from_millis(3));
+		assert_eq!(metrics.timers, 4);
+	}
+
+	#[test]
+	fn blocking() {
+		let stats = Stats::default();
+		let guard = BlockingGuard::new(stats.blocking());
+		let metrics = stats.metrics(0);
+		assert_eq!(metrics.blocking_spawned, 1);
+		assert_eq!(metrics.blocking_active, 1);
+
+		drop(guard);
+		let metrics = stats.metrics(0);
+		assert_eq!(metrics.blocking_spawned, 1);
+		assert_eq!(metrics.blocking_active, 0);
+	}
+
+	#[test]
+	fn named_tasks() {
+		let stats = Stats::default();
+		let old = stats.spawn(Some("old".into())).unwrap();
+		std::thread::sleep(Duration::from_millis(2));
+		let new = stats.spawn(Some("new".into())).unwrap();
+		stats.poll(old, Duration::from_millis(5));
+		stats.poll(old, Duration::from_millis(5));
+
+		let tasks = stats.tasks();
+		assert_eq!(tasks.len(), 2);
+		assert_eq!(tasks[0].name, "old");
+		assert_eq!(tasks[0].polls, 2);
+		assert_eq!(tasks[0].busy, Duration::from_millis(10));
+		assert_eq!(tasks[1].name, "new");
+		assert_eq!(tasks[1].polls, 0);
+
+		stats.release(Some(old));
+		stats.poll(old, Duration::from_millis(5));
+		let tasks = stats.tasks();
+		assert_eq!(tasks.len(), 1);
+		assert_eq!(tasks[0].name, "new");
+
+		stats.release(Some(new));
+		assert!(stats.tasks().is_empty());
+		assert_eq!(stats.alive(), 0);
+	}
+
+	#[test]
+	fn report_limit() {
+		let metrics = Metrics {
+			spawned: 3,
+			completed: 1,
+			pending: 2,
+			ticks: 7,
+			timers: 1,
+			..Metrics::default()
+		};
+
+		let task = |name: &'static str| TaskInfo {
+			name: name.into(),
+			age: Duration::from_secs(1),
+			busy: Duration::ZERO,
+			polls: 4,
+		};
+
+		let report = report(&metrics, &[task("first"), task("second")], 1);
+		let lines: Vec<&str> = report.lines().collect();
+		assert_eq!(lines[0], "tasks: 2 pending, 3 spawned, 1 completed");
+		assert_eq!(lines[1], "blocking: 0 active, 0 spawned");
+		assert!(lines[2].starts_with("ticks: 7, "));
+		assert_eq!(lines[3], "timers: 1");
+		assert_eq!(lines[4], "oldest tasks:");
+		assert!(lines[5].starts_with("  first: "));
+		assert!(lines[5].ends_with(", 4 polls)"));
+		assert_eq!(lines.len(), 6);
+	}
+
+	#[test]
+	fn report_without_tasks() {
+		let report = report(&Metrics::default(), &[], 10);
+		assert_eq!(report.lines().count(), 4);
+		assert!(!report.contains("oldest tasks"));
+	}
+}
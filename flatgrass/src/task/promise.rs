@@ -0,0 +1,268 @@
+use super::{ShutdownError, Suspension, is_task, report_error, settle, spawn};
+use crate::ffi;
+use crate::lua::util::{Tuple, Yield};
+use crate::lua::{FnReturn, Lua, ToLua, stack_overflow};
+use crate::prelude::*;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+#[derive(Debug)]
+enum State {
+	Pending {
+		then: Vec<Function>,
+		catch: Vec<Function>,
+		waiting: Vec<Suspension>,
+	},
+	Resolved(Tuple),
+	Rejected(Value),
+}
+
+/// A Lua object settled with the output of a future.
+///
+/// From Lua, `promise:Then(func)` and `promise:Catch(func)` register functions called with
+/// the values or the error of the future, and `promise:Wait()` yields the calling coroutine
+/// until the future completes, returning its values or `nil` followed by the error.
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub struct Promise {
+	udata: Userdata,
+}
+
+/// The key of the metatable shared by every promise in the registry.
+static METATABLE: u8 = 0;
+
+impl Promise {
+	/// Spawns a future and returns a promise settled with its output.
+	pub fn spawn<F>(future: F) -> Result<Self, ShutdownError>
+	where
+		F: Future<Output: FnReturn> + 'static,
+	{
+		let state = Rc::new(RefCell::new(State::Pending {
+			then: Vec::new(),
+			catch: Vec::new(),
+			waiting: Vec::new(),
+		}));
+
+		let promise = Self::new(state.clone());
+		spawn(async move {
+			let res = settle(future).await;
+			Self::settle(&state, res);
+		})?
+		.detach();
+
+		Ok(promise)
+	}
+
+	/// Creates the userdata of a promise, which owns a reference to its state.
+	fn new(state: Rc<RefCell<State>>) -> Self {
+		let metatable = Self::metatable();
+		Lua::get(|lua| unsafe {
+			let stack = lua.stack();
+			if !stack.check_size(2) {
+				stack_overflow!();
+			}
+
+			let udata = ffi::lua_newuserdata(lua.to_ptr(), size_of::<*const RefCell<State>>());
+			udata
+				.cast::<*const RefCell<State>>()
+				.write(Rc::into_raw(state));
+			stack.push_table(&metatable);
+			ffi::lua_setmetatable(lua.to_ptr(), -2);
+			Self {
+				udata: stack.pop_userdata_unchecked(),
+			}
+		})
+	}
+
+	/// Gets the metatable shared by every promise, creating it the first time.
+	fn metatable() -> Table {
+		let key = (&raw const METATABLE)
+			.cast_mut()
+			.cast::<ffi::libc::c_void>();
+		let registry = unsafe { Table::registry() };
+		if let Value::Table(metatable) = registry.raw_get(key) {
+			return metatable;
+		}
+
+		static GC: ffi::lua_CFunction = ffi::raw_function!(|state| unsafe {
+			let udata = ffi::lua_touserdata(state, 1).cast::<*const RefCell<State>>();
+			let ptr = std::mem::replace(&mut *udata, std::ptr::null());
+			if !ptr.is_null() {
+				Lua::enter(state, |_| drop(Rc::from_raw(ptr)));
+			}
+
+			0
+		});
+
+		let metatable = table! {
+			__index: table! {
+				Then: Function::from_fn(Self::then),
+				Catch: Function::from_fn(Self::catch),
+				Wait: Function::from_fn(Self::wait),
+			},
+			__gc: GC,
+			__metatable: "Promise",
+		};
+
+		registry.raw_set(key, &metatable);
+		metatable
+	}
+
+	/// Gets the state of a promise passed to one of its methods.
+	fn state(this: &Value) -> Result<Rc<RefCell<State>>, Value> {
+		let Value::Userdata(udata) = this else {
+			return Err("expected a promise".to_lua());
+		};
+
+		let metatable = Self::metatable();
+		Lua::get(|lua| unsafe {
+			let stack = lua.stack();
+			if !stack.check_size(2) {
+				stack_overflow!();
+			}
+
+			stack.push_userdata(udata);
+			let is_promise = ffi::lua_getmetatable(lua.to_ptr(), -1) != 0 && {
+				stack.push_table(&metatable);
+				let equal = ffi::lua_rawequal(lua.to_ptr(), -1, -2) != 0;
+				stack.pop_n(2);
+				equal
+			};
+
+			let ptr = match is_promise {
+				true => *ffi::lua_touserdata(lua.to_ptr(), -1).cast::<*const RefCell<State>>(),
+				false => std::ptr::null(),
+			};
+
+			stack.pop_n(1);
+			match ptr.is_null() {
+				true => Err("expected a promise".to_lua()),
+				false => {
+					Rc::increment_strong_count(ptr);
+					Ok(Rc::from_raw(ptr))
+				}
+			}
+		})
+	}
+
+	fn then((this, func): (Value, Function)) -> Result<Value, Value> {
+		let state = Self::state(&this)?;
+		let values = match &mut *state.borrow_mut() {
+			State::Pending { then, .. } => {
+				then.push(func);
+				return Ok(this);
+			}
+			State::Resolved(values) => values.clone(),
+			State::Rejected(_) => return Ok(this),
+		};
+
+		func.call(values)?;
+		Ok(this)
+	}
+
+	fn catch((this, func): (Value, Function)) -> Result<Value, Value> {
+		let state = Self::state(&this)?;
+		let err = match &mut *state.borrow_mut() {
+			State::Pending { catch, .. } => {
+				catch.push(func);
+				return Ok(this);
+			}
+			State::Rejected(err) => err.clone(),
+			State::Resolved(_) => return Ok(this),
+		};
+
+		func.call([err])?;
+		Ok(this)
+	}
+
+	fn wait(this: Value) -> Result<Yield<Tuple>, Value> {
+		let state = Self::state(&this)?;
+		let Some(cor) = Coroutine::running() else {
+			return Err("cannot wait for a promise outside of a coroutine".to_lua());
+		};
+
+		if is_task(&cor) {
+			return Ok(Yield([this].into_iter().collect()));
+		}
+
+		let suspension = Suspension::new(cor);
+		let res = match &mut *state.borrow_mut() {
+			State::Pending { waiting, .. } => {
+				waiting.push(suspension);
+				return Ok(Yield(Tuple::new()));
+			}
+			State::Resolved(values) => Ok(values.clone()),
+			State::Rejected(err) => Err(err.clone()),
+		};
+
+		spawn(async move { suspension.resume(res) })?.detach();
+		Ok(Yield(Tuple::new()))
+	}
+
+	fn settle(state: &RefCell<State>, res: Result<Tuple, Value>) {
+		let settled = match &res {
+			Ok(values) => State::Resolved(values.clone()),
+			Err(err) => State::Rejected(err.clone()),
+		};
+
+		let State::Pending {
+			then,
+			catch,
+			waiting,
+		} = state.replace(settled)
+		else {
+			return;
+		};
+
+		match &res {
+			Ok(values) => {
+				for func in then {
+					if let Err(err) = func.call(values.clone()) {
+						report_error(err);
+					}
+				}
+			}
+			Err(err) => {
+				if catch.is_empty() && waiting.is_empty() {
+					report_error(err.clone());
+				}
+
+				for func in catch {
+					if let Err(err) = func.call([err.clone()]) {
+						report_error(err);
+					}
+				}
+			}
+		}
+
+		for suspension in waiting {
+			suspension.resume(res.clone());
+		}
+	}
+
+	pub fn to_userdata(&self) -> &Userdata {
+		&self.udata
+	}
+}
+
+impl ToLua for Promise {
+	fn to_lua_by_ref(&self) -> Value {
+		self.udata.to_lua_by_ref()
+	}
+
+	fn to_lua(self) -> Value {
+		self.udata.to_lua()
+	}
+}
+
+/// Forgets the metatable of promises, as its functions are about to be released.\
+/// The state of the promises left is leaked rather than dropped by code that is unloaded.
+pub(super) fn release() {
+	let key = (&raw const METATABLE)
+		.cast_mut()
+		.cast::<ffi::libc::c_void>();
+	let registry = unsafe { Table::registry() };
+	if let Value::Table(metatable) = registry.raw_get(key) {
+		metatable.raw_set("__gc", Value::Nil);
+		registry.raw_set(key, Value::Nil);
+	}
+}
@@ -0,0 +1,238 @@
+use super::{report_error, time};
+use crate::ffi;
+use crate::lua::coroutine::Resume;
+use crate::lua::util::Tuple;
+use crate::lua::{Coroutine, Function, LightUserdata, ToLua, Value};
+use futures_channel::oneshot::channel;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::ffi::c_char;
+use std::rc::Rc;
+use std::time::Duration;
+
+thread_local! {
+	static TASKS: RefCell<HashSet<*mut ffi::lua_State>> = RefCell::new(HashSet::new());
+	static SUSPENDED: RefCell<HashMap<*mut ffi::lua_State, (u64, Option<YieldPoint>)>> = RefCell::new(HashMap::new());
+	static NEXT_SUSPENSION: Cell<u64> = const { Cell::new(0) };
+}
+
+static GAME_TIME: u8 = 0;
+
+/// Tags a number of seconds yielded to a task, to wait for them in game time rather than real time.
+pub(crate) fn game_time_tag() -> LightUserdata {
+	(&raw const GAME_TIME).cast_mut().cast()
+}
+
+/// Removes a coroutine from the running tasks when dropped.
+struct TaskGuard(*mut ffi::lua_State);
+
+impl Drop for TaskGuard {
+	fn drop(&mut self) {
+		TASKS.with_borrow_mut(|tasks| tasks.remove(&self.0));
+	}
+}
+
+/// Returns `true` if the coroutine is run by [`run_coroutine`], in which case it should yield
+/// the values it waits for rather than be resumed by something else.
+pub(crate) fn is_task(cor: &Coroutine) -> bool {
+	let ptr = cor.to_ptr();
+	TASKS.with_borrow(|tasks| tasks.contains(&ptr))
+}
+
+/// The innermost Lua function of a coroutine and the line it is at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct YieldPoint {
+	source: *const c_char,
+	line_defined: i32,
+	current_line: i32,
+}
+
+impl YieldPoint {
+	/// Finds where a coroutine is, skipping the C functions it is calling, such as the one yielding it.
+	fn of(ptr: *mut ffi::lua_State) -> Option<Self> {
+		unsafe {
+			let mut dbg = std::mem::zeroed();
+			let mut level = 0;
+			while ffi::lua_getstack(ptr, level, &mut dbg) != 0 {
+				ffi::lua_getinfo(ptr, c"Sl".as_ptr(), &mut dbg);
+				if dbg.currentline >= 0 {
+					return Some(Self {
+						source: dbg.source,
+						line_defined: dbg.linedefined,
+						current_line: dbg.currentline,
+					});
+				}
+
+				level += 1;
+			}
+
+			None
+		}
+	}
+}
+
+/// A coroutine yielded by a function waiting for something, to be resumed once it is ready.
+///
+/// The coroutine is only resumed if it is still suspended where it yielded, so that
+/// resuming it after something else did does not return from an unrelated `coroutine.yield`.
+#[derive(Debug)]
+pub(crate) struct Suspension {
+	cor: Coroutine,
+	ptr: *mut ffi::lua_State,
+	id: u64,
+}
+
+impl Suspension {
+	/// Marks the running coroutine as suspended by the function calling this, which must then yield it.
+	pub fn new(cor: Coroutine) -> Self {
+		let ptr = cor.to_ptr();
+		let id = NEXT_SUSPENSION.replace(NEXT_SUSPENSION.get() + 1);
+		let point = YieldPoint::of(ptr);
+		SUSPENDED.with_borrow_mut(|suspended| suspended.insert(ptr, (id, point)));
+
+		Self { cor, ptr, id }
+	}
+
+	/// Resumes the coroutine with the values, or `nil` followed by the error.
+	pub fn resume(self, res: Result<Tuple, Value>) {
+		let marked = SUSPENDED.with_borrow(|suspended| suspended.get(&self.ptr).copied());
+		let resumable = match marked {
+			Some((id, point)) if id == self.id => {
+				self.cor.is_suspended() && YieldPoint::of(self.ptr) == point
+			}
+			_ => false,
+		};
+
+		if resumable {
+			SUSPENDED.with_borrow_mut(|suspended| suspended.remove(&self.ptr));
+			let resumed = match res {
+				Ok(values) => self.cor.resume(values),
+				Err(err) => self.cor.resume([Value::Nil, err]),
+			};
+
+			if let Err(err) = resumed {
+				report_error(err);
+			}
+		} else if let Err(err) = res {
+			report_error(err);
+		}
+	}
+}
+
+impl Drop for Suspension {
+	fn drop(&mut self) {
+		SUSPENDED.with_borrow_mut(|suspended| {
+			if suspended
+				.get(&self.ptr)
+				.is_some_and(|(id, _)| *id == self.id)
+			{
+				suspended.remove(&self.ptr);
+			}
+		});
+	}
+}
+
+/// Runs a coroutine until it returns, waiting for the values it yields before resuming it.
+pub(crate) async fn run_coroutine(cor: Coroutine, args: Tuple) -> Result<Tuple, Value> {
+	let ptr = cor.to_ptr();
+	TASKS.with_borrow_mut(|tasks| tasks.insert(ptr));
+	let _guard = TaskGuard(ptr);
+	let mut resumed = cor.resume(args)?;
+	loop {
+		match resumed {
+			Resume::Return(values) => return Ok(values),
+			Resume::Yield(values) => {
+				let args = wait_for(values).await?;
+				resumed = cor.resume(args)?;
+			}
+		}
+	}
+}
+
+/// Waits for a value yielded by a coroutine, returning the values to resume it with.
+async fn wait_for(mut values: Tuple) -> Result<Tuple, Value> {
+	match values.pop_front().unwrap_or_default() {
+		Value::Nil => {
+			time::yield_now().await;
+			Ok(Tuple::new())
+		}
+		Value::Number(secs) => {
+			sleep(time::Clock::Wall, secs).await;
+			Ok(Tuple::new())
+		}
+		Value::LightUserdata(tag) if tag == game_time_tag() => {
+			match values.pop_front() {
+				Some(Value::Number(secs)) => sleep(time::Clock::Game, secs).await,
+				_ => time::yield_now().await,
+			}
+
+			Ok(Tuple::new())
+		}
+		Value::Function(func) => {
+			let (sender, receiver) = channel();
+			let sender = RefCell::new(Some(sender));
+			let resume = Function::from_fn(move |args: Tuple| {
+				if let Some(sender) = sender.borrow_mut().take() {
+					let _ = sender.send(args);
+				}
+			});
+
+			func.call([resume])?;
+			match receiver.await {
+				Ok(args) => Ok(args),
+				Err(_) => Err("the coroutine was never resumed".to_lua()),
+			}
+		}
+		value @ (Value::Table(_) | Value::Userdata(_)) => {
+			let (sender, receiver) = channel();
+			let sender = Rc::new(RefCell::new(Some(sender)));
+			let settle = move |res: Result<Tuple, Value>| {
+				if let Some(sender) = sender.borrow_mut().take() {
+					let _ = sender.send(res);
+				}
+			};
+
+			let resolve = settle.clone();
+			let resolve = Function::from_fn(move |values: Tuple| resolve(Ok(values)));
+			let reject = Function::from_fn(move |err: Value| settle(Err(err)));
+			call_method(&value, "Then", resolve)?;
+			call_method(&value, "Catch", reject)?;
+			match receiver.await {
+				Ok(Ok(values)) => Ok(values),
+				Ok(Err(err)) => Ok([Value::Nil, err].into_iter().collect()),
+				Err(_) => Err("the promise was never settled".to_lua()),
+			}
+		}
+		value => {
+			let err = format!("attempt to await a {} value", value.get_type().name());
+			Err(err.to_lua())
+		}
+	}
+}
+
+/// Waits for a number of seconds on a clock, or until the next tick if it is not positive.
+async fn sleep(clock: time::Clock, secs: f64) {
+	match Duration::try_from_secs_f64(secs) {
+		Ok(duration) if !duration.is_zero() => clock.sleep(duration).await,
+		_ => time::yield_now().await,
+	}
+}
+
+fn call_method(value: &Value, name: &str, arg: Function) -> Result<Tuple, Value> {
+	let method = match value {
+		Value::Table(table) => table.get(name)?,
+		Value::Userdata(udata) => udata.get(name)?,
+		_ => Value::Nil,
+	};
+
+	match method {
+		Value::Function(method) => method.call([value.clone(), arg.to_lua()]),
+		_ => {
+			let err = format!(
+				"attempt to await a {} value without a '{name}' method",
+				value.get_type().name()
+			);
+			Err(err.to_lua())
+		}
+	}
+}
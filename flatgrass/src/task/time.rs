@@ -1,64 +1,531 @@
-use crate::ffi;
-use crate::lua::{Function, Lua, Table, Value, call};
-use futures_channel::oneshot::{Receiver, channel};
+use crate::gm::curtime;
+use crate::lua::Lua;
+use futures_core::Stream;
+use std::cell::{Cell, RefCell};
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap};
 use std::pin::Pin;
-use std::task::{Context, Poll};
+use std::task::{Context, Poll, Waker};
 use std::time::{Duration, Instant};
 
-pub fn yield_now() -> Sleep {
-	sleep(Duration::ZERO)
+/// The clock used to measure time.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Clock {
+	/// Real time, measured with [`Instant`].
+	#[default]
+	Wall,
+	/// Game time, measured with [`CurTime`](https://wiki.facepunch.com/gmod/Global.CurTime).\
+	/// It follows the time scale of the server, and stops when the game is paused.
+	Game,
 }
 
-pub fn sleep_until(instant: Instant) -> Sleep {
-	let now = Instant::now();
-	if instant > now {
-		sleep(instant - now)
-	} else {
-		yield_now()
+impl Clock {
+	/// The current time of this clock.
+	pub fn now(self) -> Time {
+		match self {
+			Self::Wall => Time::Wall(Instant::now()),
+			Self::Game => Time::Game(curtime()),
+		}
+	}
+
+	/// Waits until the duration has elapsed on this clock.
+	pub fn sleep(self, duration: Duration) -> Sleep {
+		Sleep::new(self.now() + duration)
+	}
+
+	/// Creates a stream yielding every time the period elapses on this clock.
+	pub fn interval(self, period: Duration) -> Interval {
+		Interval {
+			period,
+			sleep: self.sleep(period),
+		}
+	}
+
+	/// Runs a future, or returns `None` if it does not complete before the duration elapses on this clock.
+	pub async fn timeout<F: IntoFuture>(self, duration: Duration, future: F) -> Option<F::Output> {
+		let mut future = std::pin::pin!(future.into_future());
+		let mut sleep = self.sleep(duration);
+		std::future::poll_fn(|cx| match future.as_mut().poll(cx) {
+			Poll::Ready(output) => Poll::Ready(Some(output)),
+			Poll::Pending => Pin::new(&mut sleep).poll(cx).map(|()| None),
+		})
+		.await
 	}
 }
 
-pub fn sleep(duration: Duration) -> Sleep {
-	static WAKE_TIMER: ffi::lua_CFunction = ffi::raw_function!(|state| unsafe {
-		Lua::enter(state, |lua| {
-			let ptr = lua.stack().get_light_userdata(ffi::lua_upvalueindex(1))?;
-			lua.async_runtime().timers.wake(ptr, ());
-			Some(())
-		});
-		0
-	});
-
-	let (sender, receiver) = channel();
-	Lua::try_get(|lua| {
-		if let Some(lua) = lua {
-			if let Value::Table(timer) = Table::globals().raw_get("timer") {
-				if let Value::Function(timer_simple) = timer.raw_get("Simple") {
-					let ptr = lua.async_runtime().timers.insert(sender);
-					let func = Function::closure(WAKE_TIMER, [ptr]);
-					let _ = call!(timer_simple: duration.as_secs_f64(), func);
-				}
-			}
+/// A point in time on a [`Clock`].
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub enum Time {
+	Wall(Instant),
+	/// Seconds since the start of the map.
+	Game(f64),
+}
+
+impl Time {
+	pub fn clock(&self) -> Clock {
+		match self {
+			Self::Wall(_) => Clock::Wall,
+			Self::Game(_) => Clock::Game,
+		}
+	}
+
+	fn has_elapsed(&self) -> bool {
+		match self {
+			Self::Wall(instant) => Instant::now() >= *instant,
+			Self::Game(time) => curtime() >= *time,
+		}
+	}
+}
+
+impl std::ops::Add<Duration> for Time {
+	type Output = Self;
+
+	fn add(self, duration: Duration) -> Self {
+		match self {
+			Self::Wall(instant) => Self::Wall(instant + duration),
+			Self::Game(time) => Self::Game(time + duration.as_secs_f64()),
 		}
-	});
+	}
+}
+
+impl From<Instant> for Time {
+	fn from(instant: Instant) -> Self {
+		Self::Wall(instant)
+	}
+}
+
+/// Yields to the runtime, resuming on the next tick.
+pub fn yield_now() -> YieldNow {
+	YieldNow { yielded: false }
+}
+
+/// Waits until the duration has elapsed in real time.
+pub fn sleep(duration: Duration) -> Sleep {
+	Clock::Wall.sleep(duration)
+}
+
+/// Waits until a point in time.
+pub fn sleep_until(time: impl Into<Time>) -> Sleep {
+	Sleep::new(time.into())
+}
+
+/// Waits until the duration has elapsed in game time.
+pub fn sleep_curtime(duration: Duration) -> Sleep {
+	Clock::Game.sleep(duration)
+}
+
+/// Creates a stream yielding every time the period elapses in real time.
+pub fn interval(period: Duration) -> Interval {
+	Clock::Wall.interval(period)
+}
+
+/// Runs a future, or returns `None` if it does not complete before the duration elapses in real time.
+pub async fn timeout<F: IntoFuture>(duration: Duration, future: F) -> Option<F::Output> {
+	Clock::Wall.timeout(duration, future).await
+}
+
+/// Runs a closure with the timers of the runtime.
+///
+/// # Panics
+///
+/// Panics if called outside of the Lua thread.
+fn with_timers<T>(func: impl FnOnce(&Timers) -> T) -> T {
+	Lua::try_get(|lua| match lua {
+		Some(lua) => func(&lua.async_runtime().timers),
+		None => panic!("timers can only be polled on the Lua thread, by the async runtime"),
+	})
+}
 
-	Sleep { receiver }
+/// Wakes a task on the next tick of the runtime.
+///
+/// # Panics
+///
+/// Panics if called outside of the Lua thread.
+pub(crate) fn wake_next_tick(waker: &Waker) {
+	with_timers(|timers| timers.next_tick.borrow_mut().push(waker.clone()));
 }
 
+/// A future returned by [`yield_now`].
+///
+/// # Panics
+///
+/// Panics if polled outside of the Lua thread, such as from a Tokio task.
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct YieldNow {
+	yielded: bool,
+}
+
+impl Future for YieldNow {
+	type Output = ();
+
+	fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+		if self.yielded {
+			Poll::Ready(())
+		} else {
+			self.yielded = true;
+			wake_next_tick(cx.waker());
+			Poll::Pending
+		}
+	}
+}
+
+/// A future returned by [`sleep`] and its variants.
+///
+/// # Panics
+///
+/// Panics if polled outside of the Lua thread before its deadline, such as from a Tokio task.
 #[derive(Debug)]
-#[repr(transparent)]
 #[must_use = "futures do nothing unless you `.await` or poll them"]
 pub struct Sleep {
-	receiver: Receiver<()>,
+	deadline: Time,
+	id: Option<u64>,
+}
+
+impl Sleep {
+	fn new(deadline: Time) -> Self {
+		Self { deadline, id: None }
+	}
+
+	/// The point in time at which this future completes.
+	pub fn deadline(&self) -> Time {
+		self.deadline
+	}
+
+	/// Changes the point in time at which this future completes.
+	pub fn reset(&mut self, deadline: impl Into<Time>) {
+		self.unregister();
+		self.deadline = deadline.into();
+	}
+
+	fn unregister(&mut self) {
+		if let Some(id) = self.id.take() {
+			Lua::try_get(|lua| {
+				if let Some(lua) = lua {
+					lua.async_runtime().timers.wakers.borrow_mut().remove(&id);
+				}
+			});
+		}
+	}
 }
 
 impl Future for Sleep {
 	type Output = ();
 
 	fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
-		match Pin::new(&mut self.receiver).poll(cx) {
-			Poll::Ready(Err(_)) => panic!("sleep channel closed unexpectedly"),
-			Poll::Ready(Ok(_)) => Poll::Ready(()),
+		if self.deadline.has_elapsed() {
+			self.unregister();
+			Poll::Ready(())
+		} else {
+			with_timers(|timers| {
+				let mut wakers = timers.wakers.borrow_mut();
+				match self.id.and_then(|id| wakers.get_mut(&id)) {
+					Some(waker) => waker.clone_from(cx.waker()),
+					None => {
+						let id = timers.insert(self.deadline);
+						wakers.insert(id, cx.waker().clone());
+						self.id = Some(id);
+					}
+				}
+			});
+
+			Poll::Pending
+		}
+	}
+}
+
+impl Drop for Sleep {
+	fn drop(&mut self) {
+		self.unregister();
+	}
+}
+
+/// A stream yielding every time a period elapses, created with [`interval`] or [`Clock::interval`].
+///
+/// Missed ticks are skipped, the next one being scheduled a period after it yields.
+///
+/// # Panics
+///
+/// Panics if polled outside of the Lua thread, like [`Sleep`].
+#[derive(Debug)]
+#[must_use = "streams do nothing unless polled"]
+pub struct Interval {
+	period: Duration,
+	sleep: Sleep,
+}
+
+impl Interval {
+	pub fn period(&self) -> Duration {
+		self.period
+	}
+
+	/// Waits for the next tick of the interval.
+	pub async fn tick(&mut self) -> Time {
+		std::future::poll_fn(|cx| self.poll_tick(cx)).await
+	}
+
+	pub fn poll_tick(&mut self, cx: &mut Context) -> Poll<Time> {
+		match Pin::new(&mut self.sleep).poll(cx) {
 			Poll::Pending => Poll::Pending,
+			Poll::Ready(()) => {
+				let now = self.sleep.deadline.clock().now();
+				self.sleep.reset(now + self.period);
+				Poll::Ready(now)
+			}
 		}
 	}
 }
+
+impl Stream for Interval {
+	type Item = Time;
+
+	fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Time>> {
+		self.poll_tick(cx).map(Some)
+	}
+}
+
+#[derive(Debug)]
+struct Entry<T> {
+	deadline: T,
+	id: u64,
+}
+
+/// A deadline of a timer, totally ordered so that timers are kept in order.
+trait Deadline {
+	fn total_cmp(&self, other: &Self) -> Ordering;
+}
+
+impl Deadline for Instant {
+	fn total_cmp(&self, other: &Self) -> Ordering {
+		self.cmp(other)
+	}
+}
+
+/// Game times are ordered with [`f64::total_cmp`], so a NaN deadline never elapses, unless its sign is negative.
+impl Deadline for f64 {
+	fn total_cmp(&self, other: &Self) -> Ordering {
+		Self::total_cmp(self, other)
+	}
+}
+
+impl<T: Deadline> PartialEq for Entry<T> {
+	fn eq(&self, other: &Self) -> bool {
+		self.cmp(other) == Ordering::Equal
+	}
+}
+
+impl<T: Deadline> Eq for Entry<T> {}
+
+impl<T: Deadline> PartialOrd for Entry<T> {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+impl<T: Deadline> Ord for Entry<T> {
+	fn cmp(&self, other: &Self) -> Ordering {
+		self.deadline
+			.total_cmp(&other.deadline)
+			.then(self.id.cmp(&other.id))
+	}
+}
+
+/// The timers of the async runtime, fired when it ticks.
+#[derive(Debug, Default)]
+pub(super) struct Timers {
+	next_id: Cell<u64>,
+	wall: RefCell<BinaryHeap<Reverse<Entry<Instant>>>>,
+	game: RefCell<BinaryHeap<Reverse<Entry<f64>>>>,
+	wakers: RefCell<HashMap<u64, Waker>>,
+	next_tick: RefCell<Vec<Waker>>,
+}
+
+impl Timers {
+	fn insert(&self, deadline: Time) -> u64 {
+		let id = self.next_id.get();
+		self.next_id.set(id + 1);
+		match deadline {
+			Time::Wall(deadline) => self.wall.borrow_mut().push(Reverse(Entry { deadline, id })),
+			Time::Game(deadline) => self.game.borrow_mut().push(Reverse(Entry { deadline, id })),
+		}
+
+		id
+	}
+
+	/// Wakes the tasks that yielded, and those whose deadline has elapsed.
+	pub fn fire(&self) {
+		let mut wakers = std::mem::take(&mut *self.next_tick.borrow_mut());
+		Self::expired(&self.wall, Instant::now(), &mut wakers, &self.wakers);
+		if !self.game.borrow().is_empty() {
+			Self::expired(&self.game, curtime(), &mut wakers, &self.wakers);
+		}
+
+		for waker in wakers {
+			waker.wake();
+		}
+	}
+
+	fn expired<T: Deadline>(
+		heap: &RefCell<BinaryHeap<Reverse<Entry<T>>>>,
+		now: T,
+		expired: &mut Vec<Waker>,
+		wakers: &RefCell<HashMap<u64, Waker>>,
+	) {
+		let mut heap = heap.borrow_mut();
+		let mut wakers = wakers.borrow_mut();
+		while let Some(Reverse(entry)) = heap.peek() {
+			if entry.deadline.total_cmp(&now) == Ordering::Greater {
+				break;
+			}
+
+			let id = entry.id;
+			heap.pop();
+			expired.extend(wakers.remove(&id));
+		}
+	}
+
+	/// The earliest deadline of the wall-clock timers still waiting to fire.
+	pub fn next_wall(&self) -> Option<Instant> {
+		let mut wall = self.wall.borrow_mut();
+		let wakers = self.wakers.borrow();
+		while let Some(Reverse(entry)) = wall.peek() {
+			if wakers.contains_key(&entry.id) {
+				return Some(entry.deadline);
+			}
+
+			wall.pop();
+		}
+
+		None
+	}
+
+	/// The number of timers waiting to fire, including tasks that yielded.
+	pub fn len(&self) -> usize {
+		self.wakers.borrow().len() + self.next_tick.borrow().len()
+	}
+
+	/// Drops every timer.
+	pub fn clear(&self) {
+		self.wall.borrow_mut().clear();
+		self.game.borrow_mut().clear();
+		self.wakers.borrow_mut().clear();
+		self.next_tick.borrow_mut().clear();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::sync::{Arc, Mutex};
+	use std::task::Wake;
+
+	struct Recorder {
+		id: usize,
+		woken: Arc<Mutex<Vec<usize>>>,
+	}
+
+	impl Wake for Recorder {
+		fn wake(self: Arc<Self>) {
+			self.woken.lock().unwrap().push(self.id);
+		}
+	}
+
+	fn waker(id: usize, woken: &Arc<Mutex<Vec<usize>>>) -> Waker {
+		let woken = woken.clone();
+		Waker::from(Arc::new(Recorder { id, woken }))
+	}
+
+	fn register(timers: &Timers, deadline: Instant, waker: Waker) -> u64 {
+		let id = timers.insert(Time::Wall(deadline));
+		timers.wakers.borrow_mut().insert(id, waker);
+		id
+	}
+
+	#[test]
+	fn fire_in_order() {
+		let timers = Timers::default();
+		let woken = Arc::default();
+		let now = Instant::now();
+		register(&timers, now, waker(2, &woken));
+		register(&timers, now - Duration::from_secs(1), waker(0, &woken));
+		register(&timers, now, waker(3, &woken));
+		register(&timers, now - Duration::from_millis(1), waker(1, &woken));
+		assert_eq!(timers.len(), 4);
+
+		timers.fire();
+		assert_eq!(*woken.lock().unwrap(), [0, 1, 2, 3]);
+		assert_eq!(timers.len(), 0);
+	}
+
+	#[test]
+	fn fire_expired() {
+		let timers = Timers::default();
+		let woken = Arc::default();
+		let now = Instant::now();
+		register(&timers, now, waker(0, &woken));
+		register(&timers, now + Duration::from_secs(60), waker(1, &woken));
+		timers.next_tick.borrow_mut().push(waker(2, &woken));
+		assert_eq!(timers.next_wall(), Some(now));
+
+		timers.fire();
+		assert_eq!(*woken.lock().unwrap(), [2, 0]);
+		assert_eq!(timers.len(), 1);
+		assert_eq!(timers.next_wall(), Some(now + Duration::from_secs(60)));
+
+		timers.fire();
+		assert_eq!(woken.lock().unwrap().len(), 2);
+	}
+
+	#[test]
+	fn unregistered() {
+		let timers = Timers::default();
+		let woken = Arc::default();
+		let now = Instant::now();
+		let id = register(&timers, now, waker(0, &woken));
+		register(&timers, now + Duration::from_secs(60), waker(1, &woken));
+		timers.wakers.borrow_mut().remove(&id);
+		assert_eq!(timers.next_wall(), Some(now + Duration::from_secs(60)));
+
+		timers.fire();
+		assert!(woken.lock().unwrap().is_empty());
+
+		timers.clear();
+		assert_eq!(timers.len(), 0);
+		assert_eq!(timers.next_wall(), None);
+	}
+
+	#[test]
+	fn entry_order() {
+		let entry = |deadline, id| Entry { deadline, id };
+		assert!(entry(1.0, 1) < entry(2.0, 0));
+		assert!(entry(1.0, 0) < entry(1.0, 1));
+		assert!(entry(f64::INFINITY, 0) < entry(f64::NAN, 0));
+		assert!(entry(-f64::NAN, 1) < entry(f64::NEG_INFINITY, 0));
+		assert_eq!(entry(f64::NAN, 0), entry(f64::NAN, 0));
+		assert_ne!(entry(f64::NAN, 0), entry(2.0, 0));
+	}
+
+	#[test]
+	fn nan_deadlines() {
+		let woken = Arc::default();
+		let wakers = RefCell::new(HashMap::new());
+		let mut heap = BinaryHeap::new();
+		for (id, deadline) in [3.0, f64::NAN, 1.0, -f64::NAN, 2.0].into_iter().enumerate() {
+			let id = id as u64;
+			heap.push(Reverse(Entry { deadline, id }));
+			wakers.borrow_mut().insert(id, waker(id as usize, &woken));
+		}
+
+		let heap = RefCell::new(heap);
+		let mut expired = Vec::new();
+		Timers::expired(&heap, 2.0, &mut expired, &wakers);
+		expired.into_iter().for_each(Waker::wake);
+		assert_eq!(*woken.lock().unwrap(), [3, 2, 4]);
+
+		let mut expired = Vec::new();
+		Timers::expired(&heap, f64::INFINITY, &mut expired, &wakers);
+		expired.into_iter().for_each(Waker::wake);
+		assert_eq!(*woken.lock().unwrap(), [3, 2, 4, 0]);
+		assert_eq!(heap.borrow().len(), 1);
+	}
+}